@@ -0,0 +1,97 @@
+//! Tone Adjustment Module
+//!
+//! Adjusts brightness, contrast and gamma of the resized image before
+//! characters are selected, so photos that would otherwise map to a wall of
+//! `@` or blank space use more of the character ramp.
+//!
+//! Adjustments are applied per channel through a 256-entry lookup table in
+//! this order: auto-levels stretch, contrast, brightness, gamma.
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::types::{calculate_luminance, LuminanceModel};
+
+/// Tone adjustment parameters
+#[derive(Clone, Copy, Debug)]
+pub struct Tone {
+    /// Additive brightness offset (-1.0 to 1.0, 0.0 = unchanged)
+    pub brightness: f32,
+    /// Contrast multiplier around mid-gray (1.0 = unchanged)
+    pub contrast: f32,
+    /// Gamma (1.0 = unchanged, > 1.0 brightens midtones)
+    pub gamma: f32,
+    /// Input luminance range stretched to 0-255 (from auto-levels)
+    pub levels: Option<(f32, f32)>,
+}
+
+impl Tone {
+    /// True when applying the adjustment would not change any pixel
+    pub fn is_identity(&self) -> bool {
+        self.brightness == 0.0 && self.contrast == 1.0 && self.gamma == 1.0 && self.levels.is_none()
+    }
+}
+
+/// Find the luminance values at the given low and high percentiles
+///
+/// # Arguments
+/// * `img` - Input image
+/// * `low` - Low percentile (0-100)
+/// * `high` - High percentile (0-100)
+/// * `luminance` - Luminance model the percentiles are taken on
+///
+/// # Returns
+/// (low, high) luminance values (0.0-255.0)
+pub fn luminance_percentiles(img: &DynamicImage, low: f32, high: f32, luminance: &LuminanceModel) -> (f32, f32) {
+    let mut histogram = [0u32; 256];
+    for (_, _, pixel) in img.pixels() {
+        let [r, g, b, _] = pixel.0;
+        histogram[calculate_luminance(r, g, b, luminance) as usize] += 1;
+    }
+
+    let total = img.width() as f32 * img.height() as f32;
+    let find = |pct: f32| {
+        let target = total * pct.clamp(0.0, 100.0) / 100.0;
+        let mut count = 0.0;
+        for (value, &n) in histogram.iter().enumerate() {
+            count += n as f32;
+            if count >= target {
+                return value as f32;
+            }
+        }
+        255.0
+    };
+
+    (find(low), find(high))
+}
+
+/// Apply tone adjustments to every pixel, preserving alpha
+pub fn adjust_tone(img: &DynamicImage, tone: &Tone) -> DynamicImage {
+    let lut = build_lut(tone);
+    let (width, height) = img.dimensions();
+    let mut output = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        output.put_pixel(x, y, Rgba([lut[r as usize], lut[g as usize], lut[b as usize], a]));
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Build the per-channel lookup table for the given adjustments
+fn build_lut(tone: &Tone) -> [u8; 256] {
+    let (low, high) = tone.levels.unwrap_or((0.0, 255.0));
+    // Avoid dividing by zero on flat images
+    let range = (high - low).max(1.0);
+
+    let mut lut = [0u8; 256];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        let mut v = (i as f32 - low) / range;
+        v = (v - 0.5) * tone.contrast + 0.5;
+        v += tone.brightness;
+        v = v.clamp(0.0, 1.0).powf(1.0 / tone.gamma);
+        *entry = (v * 255.0).round() as u8;
+    }
+
+    lut
+}
@@ -0,0 +1,47 @@
+//! Alpha Handling Module
+//!
+//! Transparent pixels keep whatever RGB the encoder stored, often black,
+//! which would otherwise turn see-through areas of logos and icons into
+//! solid `@` blocks. Images are flattened onto a background color before
+//! any other processing, and cells whose coverage falls below an alpha
+//! cutoff can be turned into blank cells that render with no color at all.
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::types::AsciiCell;
+
+/// Blend every pixel over `background` by its alpha
+///
+/// The alpha channel itself is kept so transparent cells can still be
+/// found after resizing.
+pub fn flatten_alpha(img: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let mut output = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let alpha = a as f32 / 255.0;
+        let [r, g, b] = [(r, background[0]), (g, background[1]), (b, background[2])]
+            .map(|(fg, bg)| (fg as f32 * alpha + bg as f32 * (1.0 - alpha)).round() as u8);
+        output.put_pixel(x, y, Rgba([r, g, b, a]));
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Replace cells whose alpha is below `cutoff` with blank cells
+///
+/// # Arguments
+/// * `cells` - Cell grid, modified in place
+/// * `alpha` - Image at the grid's resolution whose alpha channel is read
+/// * `cutoff` - Minimum alpha (0-255) for a cell to keep its character
+pub fn blank_transparent(cells: &mut [Vec<AsciiCell>], alpha: &DynamicImage, cutoff: u8) {
+    for (y, row) in cells.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let (x, y) = (x as u32, y as u32);
+            if x < alpha.width() && y < alpha.height() && alpha.get_pixel(x, y).0[3] < cutoff {
+                *cell = AsciiCell::blank();
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Two-Tone Conversion Module
+//!
+//! Renders each cell as either ink or paper, for stencils and printer-friendly
+//! output. Where `generate_ascii_with_charset` spreads brightness over a ramp
+//! of glyphs, this picks one of two by comparing each cell's darkness with a
+//! threshold: a fixed level, Otsu's method over the whole image, or the mean
+//! of a window around the cell (adaptive, for uneven lighting). Ordered or
+//! error-diffusion dithering can be added to keep some tonal range.
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::cli::{Binarize, Dither};
+use crate::edge::otsu_threshold;
+use crate::palette::BAYER_4;
+use crate::types::{calculate_luminance, AsciiCell, LuminanceModel};
+
+/// Settings for ink/paper conversion
+#[derive(Clone, Debug)]
+pub struct BinaryOptions {
+    /// How the ink threshold is chosen
+    pub method: Binarize,
+    /// Luminance below which a cell is ink, for the global method
+    pub level: u8,
+    /// Neighborhood size in cells, for the adaptive method
+    pub window: u32,
+    /// How much darker than the neighborhood mean ink must be (adaptive)
+    pub offset: f32,
+    /// Dithering applied before thresholding
+    pub dither: Dither,
+    /// Glyph for ink cells
+    pub ink: char,
+    /// Glyph for paper cells
+    pub paper: char,
+    /// Treat bright pixels as ink (light text on a dark background)
+    pub invert: bool,
+    /// Luminance model the threshold is applied to
+    pub luminance: LuminanceModel,
+}
+
+/// Converts an image into ink and paper cells
+///
+/// # Arguments
+/// * `img` - Image with one pixel per output cell
+/// * `options` - Threshold method, glyphs and dithering
+///
+/// # Returns
+/// The cell grid and the threshold used, in darkness (0 = paper white).
+/// The adaptive method has no single threshold and returns None.
+pub fn generate_ascii_binary(img: &DynamicImage, options: &BinaryOptions) -> (Vec<Vec<AsciiCell>>, Option<u8>) {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+
+    // Work in darkness so ink is always the upper class
+    let mut darkness: Vec<Vec<f32>> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let [r, g, b, _] = img.get_pixel(x as u32, y as u32).0;
+                    let brightness = calculate_luminance(r, g, b, &options.luminance);
+                    if options.invert { brightness } else { 255.0 - brightness }
+                })
+                .collect()
+        })
+        .collect();
+
+    let (cutoffs, threshold) = match options.method {
+        Binarize::Global => {
+            // Ink below the level in luminance, or above it when inverted
+            let t = if options.invert { options.level } else { 255 - options.level };
+            (vec![vec![t as f32; width]; height], Some(t))
+        }
+        Binarize::Otsu => {
+            let mut histogram = [0u32; 256];
+            for &d in darkness.iter().flatten() {
+                histogram[d.clamp(0.0, 255.0) as usize] += 1;
+            }
+            // Otsu's method needs two classes; a flat image is split at mid-gray
+            let populated = histogram.iter().filter(|&&n| n > 0).count();
+            let t = if populated < 2 { 128 } else { otsu_threshold(&histogram) };
+            // Otsu's threshold is the first value of the upper class
+            (vec![vec![(t as f32 - 0.5).max(0.0); width]; height], Some(t))
+        }
+        Binarize::Adaptive => (local_means(&darkness, options.window, options.offset), None),
+    };
+
+    let mut rows = Vec::with_capacity(height);
+    for y in 0..height {
+        let mut row = Vec::with_capacity(width);
+
+        for x in 0..width {
+            let mut value = darkness[y][x];
+            if let Dither::Bayer = options.dither {
+                value += ((BAYER_4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5) * 255.0;
+            }
+
+            let ink = value > cutoffs[y][x];
+
+            if let Dither::FloydSteinberg = options.dither {
+                let error = value.clamp(0.0, 255.0) - if ink { 255.0 } else { 0.0 };
+                let mut spread_error = |dx: isize, dy: usize, weight: f32| {
+                    let next = x.checked_add_signed(dx).and_then(|nx| darkness.get_mut(y + dy)?.get_mut(nx));
+                    if let Some(next) = next {
+                        *next += error * weight;
+                    }
+                };
+                spread_error(1, 0, 7.0 / 16.0);
+                spread_error(-1, 1, 3.0 / 16.0);
+                spread_error(0, 1, 5.0 / 16.0);
+                spread_error(1, 1, 1.0 / 16.0);
+            }
+
+            let [r, g, b, _] = img.get_pixel(x as u32, y as u32).0;
+            let ch = if ink { options.ink } else { options.paper };
+            row.push(AsciiCell::new(ch, r, g, b));
+        }
+
+        rows.push(row);
+    }
+
+    (rows, threshold)
+}
+
+/// Mean darkness of the `window`-sized box around each cell, raised by `offset`
+///
+/// Uses a summed-area table so the cost does not depend on the window size.
+/// Boxes are clipped at the image border.
+fn local_means(darkness: &[Vec<f32>], window: u32, offset: f32) -> Vec<Vec<f32>> {
+    let height = darkness.len();
+    let width = darkness.first().map_or(0, Vec::len);
+
+    let mut integral = vec![vec![0.0f64; width + 1]; height + 1];
+    for y in 0..height {
+        for x in 0..width {
+            integral[y + 1][x + 1] =
+                darkness[y][x] as f64 + integral[y][x + 1] + integral[y + 1][x] - integral[y][x];
+        }
+    }
+
+    let radius = (window / 2) as usize;
+    (0..height)
+        .map(|y| {
+            let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+            (0..width)
+                .map(|x| {
+                    let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+                    let sum = integral[bottom][right] - integral[top][right] - integral[bottom][left]
+                        + integral[top][left];
+                    let count = ((bottom - top) * (right - left)) as f64;
+                    (sum / count) as f32 + offset
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn options(method: Binarize) -> BinaryOptions {
+        BinaryOptions {
+            method,
+            level: 128,
+            window: 3,
+            offset: 8.0,
+            dither: Dither::None,
+            ink: '#',
+            paper: '.',
+            invert: false,
+            luminance: LuminanceModel::Bt601,
+        }
+    }
+
+    /// A single row of gray pixels
+    fn row(values: &[u8]) -> DynamicImage {
+        let img = RgbaImage::from_fn(values.len() as u32, 1, |x, _| {
+            let v = values[x as usize];
+            Rgba([v, v, v, 255])
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    fn glyphs(img: &DynamicImage, options: &BinaryOptions) -> (String, Option<u8>) {
+        let (cells, threshold) = generate_ascii_binary(img, options);
+        (cells.iter().flatten().map(|c| c.ch).collect(), threshold)
+    }
+
+    #[test]
+    fn global_cutoff_is_the_level() {
+        let img = row(&[0, 127, 128, 255]);
+        assert_eq!(glyphs(&img, &options(Binarize::Global)), ("##..".to_string(), Some(127)));
+
+        // Inverted, ink is strictly above the level
+        let inverted = BinaryOptions { invert: true, ..options(Binarize::Global) };
+        assert_eq!(glyphs(&img, &inverted), ("...#".to_string(), Some(128)));
+    }
+
+    #[test]
+    fn otsu_splits_two_tones() {
+        let img = row(&[20, 30, 220, 230]);
+        let (glyphs, threshold) = glyphs(&img, &options(Binarize::Otsu));
+        assert_eq!(glyphs, "##..");
+        assert!(threshold.is_some());
+    }
+
+    #[test]
+    fn otsu_on_flat_images_splits_at_mid_gray() {
+        assert_eq!(glyphs(&row(&[255; 4]), &options(Binarize::Otsu)), ("....".to_string(), Some(128)));
+        assert_eq!(glyphs(&row(&[0; 4]), &options(Binarize::Otsu)), ("####".to_string(), Some(128)));
+    }
+
+    #[test]
+    fn adaptive_cutoff_follows_the_neighborhood() {
+        let img = row(&[200, 200, 50, 200, 200]);
+        assert_eq!(glyphs(&img, &options(Binarize::Adaptive)), ("..#..".to_string(), None));
+
+        // Uniform areas stay paper, however dark
+        assert_eq!(glyphs(&row(&[40; 5]), &options(Binarize::Adaptive)), (".....".to_string(), None));
+    }
+}
@@ -0,0 +1,174 @@
+//! Block Element Module
+//!
+//! Renders each cell as a Unicode block element split into sub-cells: 2×2
+//! quadrants (`▘▝▀▖▌▞▛…`) or 2×3 sextants from Symbols for Legacy Computing.
+//! Every cell gets a foreground and a background color, so a cell can show
+//! two colors at twice (quadrants) or six times (sextants) the resolution of
+//! a plain character while areas stay solid.
+//!
+//! For each cell every split of its sub-cells into two groups is tried; the
+//! split whose group means leave the smallest squared RGB error wins. The
+//! darker group is drawn as the glyph (the lighter one with `invert`), so the
+//! pattern still reads correctly when colors are not shown.
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::cli::Blocks;
+use crate::types::{calculate_luminance, AsciiCell, LuminanceModel};
+
+/// Quadrant glyphs indexed by pattern: bit 0 top-left, 1 top-right,
+/// 2 bottom-left, 3 bottom-right
+const QUADRANT_GLYPHS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Sub-cell columns and rows per character cell
+pub fn sub_cells(mode: &Blocks) -> (u32, u32) {
+    match mode {
+        Blocks::Quadrant => (2, 2),
+        Blocks::Sextant => (2, 3),
+    }
+}
+
+/// Every glyph of a block mode, from empty to full
+pub fn block_charset(mode: &Blocks) -> String {
+    let (cols, rows) = sub_cells(mode);
+    (0..1 << (cols * rows)).map(|pattern| glyph(mode, pattern)).collect()
+}
+
+/// Converts an image at sub-cell resolution into block element cells
+///
+/// # Arguments
+/// * `img` - Image with 2×2 (quadrant) or 2×3 (sextant) pixels per output cell
+/// * `mode` - Block element set
+/// * `invert` - Draw the lighter group as the glyph (light on a dark background)
+/// * `luminance` - Luminance model deciding which group is darker
+///
+/// # Returns
+/// Cells carrying the glyph color as foreground and the rest as background
+pub fn generate_blocks(
+    img: &DynamicImage,
+    mode: &Blocks,
+    invert: bool,
+    luminance: &LuminanceModel,
+) -> Vec<Vec<AsciiCell>> {
+    let (sub_cols, sub_rows) = sub_cells(mode);
+    let cols = img.width() / sub_cols;
+    let rows = img.height() / sub_rows;
+    let full = (1 << (sub_cols * sub_rows)) - 1;
+
+    // Ink is the darker group, or the lighter one when inverted
+    let is_ink = |a: [f32; 3], b: [f32; 3]| {
+        let (la, lb) = (lum(a, luminance), lum(b, luminance));
+        if invert { la >= lb } else { la <= lb }
+    };
+
+    let mut grid = Vec::with_capacity(rows as usize);
+
+    for row in 0..rows {
+        let mut cells = Vec::with_capacity(cols as usize);
+
+        for col in 0..cols {
+            let pixels: Vec<[f32; 3]> = (0..sub_rows)
+                .flat_map(|sy| (0..sub_cols).map(move |sx| (sx, sy)))
+                .map(|(sx, sy)| {
+                    let [r, g, b, _] = img.get_pixel(col * sub_cols + sx, row * sub_rows + sy).0;
+                    [r, g, b].map(|c| c as f32)
+                })
+                .collect();
+
+            // A uniform cell is the baseline; a split must do strictly better
+            let mean = average(&pixels, full, true);
+            let mut best = (squared_error(&pixels, full, mean, mean), full);
+            for pattern in 1..full {
+                let (fg, bg) = (average(&pixels, pattern, true), average(&pixels, pattern, false));
+                let error = squared_error(&pixels, pattern, fg, bg);
+                if error < best.0 {
+                    best = (error, pattern);
+                }
+            }
+
+            let pattern = best.1;
+            let cell = if pattern == full {
+                // Solid cell: a full block if it is on the ink side of mid-gray
+                let ch = if is_ink(mean, [127.5; 3]) { glyph(mode, full) } else { ' ' };
+                AsciiCell::with_background(ch, to_rgb(mean), to_rgb(mean))
+            } else {
+                let (fg, bg) = (average(&pixels, pattern, true), average(&pixels, pattern, false));
+                if is_ink(fg, bg) {
+                    AsciiCell::with_background(glyph(mode, pattern), to_rgb(fg), to_rgb(bg))
+                } else {
+                    AsciiCell::with_background(glyph(mode, full ^ pattern), to_rgb(bg), to_rgb(fg))
+                }
+            };
+            cells.push(cell);
+        }
+
+        grid.push(cells);
+    }
+
+    grid
+}
+
+/// Glyph for a pattern of filled sub-cells, numbered row by row from the
+/// top-left, one bit each
+fn glyph(mode: &Blocks, pattern: usize) -> char {
+    match mode {
+        Blocks::Quadrant => QUADRANT_GLYPHS[pattern],
+        Blocks::Sextant => sextant_glyph(pattern),
+    }
+}
+
+/// Sextant glyph for a 6-bit pattern
+///
+/// U+1FB00..U+1FB3B hold the sextants in pattern order, leaving out the
+/// empty and full cells and the left and right halves, which already exist
+/// as space, `█`, `▌` and `▐`.
+fn sextant_glyph(pattern: usize) -> char {
+    match pattern {
+        0 => ' ',
+        21 => '▌',
+        42 => '▐',
+        63 => '█',
+        n => {
+            let index = n - 1 - usize::from(n > 21) - usize::from(n > 42);
+            char::from_u32(0x1FB00 + index as u32).unwrap_or('?')
+        }
+    }
+}
+
+/// Mean color of the sub-cells inside (`inside` true) or outside a pattern
+fn average(pixels: &[[f32; 3]], pattern: usize, inside: bool) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    let mut count = 0.0;
+    for (i, p) in pixels.iter().enumerate() {
+        if (pattern >> i & 1 == 1) == inside {
+            for c in 0..3 {
+                sum[c] += p[c];
+            }
+            count += 1.0;
+        }
+    }
+    sum.map(|s| s / count)
+}
+
+/// Squared RGB error of drawing a pattern with the given colors
+fn squared_error(pixels: &[[f32; 3]], pattern: usize, fg: [f32; 3], bg: [f32; 3]) -> f32 {
+    pixels
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let target = if pattern >> i & 1 == 1 { fg } else { bg };
+            (0..3).map(|c| (p[c] - target[c]) * (p[c] - target[c])).sum::<f32>()
+        })
+        .sum()
+}
+
+fn lum(color: [f32; 3], luminance: &LuminanceModel) -> f32 {
+    let [r, g, b] = to_rgb(color);
+    calculate_luminance(r, g, b, luminance)
+}
+
+fn to_rgb(color: [f32; 3]) -> [u8; 3] {
+    color.map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
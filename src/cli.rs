@@ -4,45 +4,726 @@
 //! for the img2ascii application using the clap parser.
 
 use clap::{Parser, ValueEnum};
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::color::parse_hex_color;
+use crate::types::LuminanceModel;
 
 // Define the output format enum
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     Txt,
     Html,
     Ansi,
+    Json,
+}
+
+// Define the input format enum
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// Pick from the file extension (.txt, .ansi, .json), otherwise image
+    Auto,
+    Image,
+    Txt,
+    Ansi,
+    Json,
+}
+
+// Define the histogram equalization method enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Equalize {
+    /// Global histogram equalization
+    Global,
+    /// Contrast-limited adaptive histogram equalization
+    Clahe,
+}
+
+// Define the terminal/page theme enum
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Dark text on a light background
+    Light,
+    /// Light text on a dark background
+    Dark,
+}
+
+// Define the perceptual color space enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// OKLab (more uniform hue and lightness)
+    Oklab,
+    /// CIELAB (L*a*b*, D65 white point)
+    Lab,
+}
+
+// Define the adaptive palette method enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMethod {
+    /// Recursively split the color box with the widest range at its median
+    MedianCut,
+    /// Median cut refined by k-means clustering
+    Kmeans,
+}
+
+// Define the dithering method enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dither {
+    /// Nearest color only
+    None,
+    /// Error diffusion to neighbouring cells
+    FloydSteinberg,
+    /// Ordered 4x4 Bayer pattern
+    Bayer,
+}
+
+// Define the shape matching metric enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShapeMetric {
+    /// Mean squared error of ink coverage
+    Mse,
+    /// Structural similarity (favors matching structure over matching density)
+    Ssim,
+}
+
+// Define the edge detection algorithm enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeAlgo {
+    /// Single threshold on Sobel gradient magnitude
+    Sobel,
+    /// Gaussian blur, non-maximum suppression and hysteresis (thin contours)
+    Canny,
+}
+
+// Define the edge gradient operator enum
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeOperator {
+    Sobel,
+    /// Sobel-like with better rotational symmetry
+    Scharr,
+    Prewitt,
+    /// 2×2 Roberts cross
+    Roberts,
+    /// Laplacian of Gaussian with zero-crossing detection
+    Log,
+}
+
+// Define the automatic edge threshold method enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeAuto {
+    /// Otsu's method on the gradient magnitude histogram
+    Otsu,
+    /// Keep the strongest --edge-density percent of pixels
+    Density,
+}
+
+/// Edge threshold: a fixed value or "auto"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeThreshold {
+    Fixed(u8),
+    Auto,
+}
+
+impl fmt::Display for EdgeThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeThreshold::Fixed(t) => write!(f, "{}", t),
+            EdgeThreshold::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl Serialize for EdgeThreshold {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EdgeThreshold::Fixed(t) => serializer.serialize_u8(*t),
+            EdgeThreshold::Auto => serializer.serialize_str("auto"),
+        }
+    }
+}
+
+// Define the resize mode enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resize {
+    /// Keep the aspect ratio inside the grid, padding with blank cells
+    Fit,
+    /// Keep the aspect ratio and cover the grid, cropping the overflow
+    Fill,
+    /// Use the exact width and height, distorting the image
+    Stretch,
+}
+
+// Define the flip direction enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flip {
+    /// Mirror left to right
+    H,
+    /// Mirror top to bottom
+    V,
+}
+
+/// A crop coordinate or size, in pixels or as a percentage of the image
+#[derive(Debug, Clone, Copy)]
+pub enum Extent {
+    Pixels(u32),
+    Percent(f32),
+}
+
+impl Extent {
+    /// Resolve against the full length of the image along this axis
+    pub fn resolve(&self, full: u32) -> u32 {
+        match self {
+            Extent::Pixels(px) => *px,
+            Extent::Percent(pct) => (full as f32 * pct / 100.0).round() as u32,
+        }
+    }
+}
+
+impl fmt::Display for Extent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Extent::Pixels(px) => write!(f, "{}", px),
+            Extent::Percent(pct) => write!(f, "{}%", pct),
+        }
+    }
+}
+
+/// Crop rectangle from --crop x,y,w,h
+#[derive(Debug, Clone, Copy)]
+pub struct Crop {
+    pub x: Extent,
+    pub y: Extent,
+    pub width: Extent,
+    pub height: Extent,
+}
+
+impl fmt::Display for Crop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl Serialize for Crop {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// One step of --filter-chain
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    /// Gaussian blur with the given sigma
+    Blur(f32),
+    /// Unsharp mask with the given blur sigma
+    Sharpen(f32),
+    /// Reduce each channel to the given number of levels
+    Posterize(u8),
+    /// Black below the given luminance, white at or above it
+    Threshold(u8),
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Blur(sigma) => write!(f, "blur:{}", sigma),
+            Filter::Sharpen(sigma) => write!(f, "sharpen:{}", sigma),
+            Filter::Posterize(levels) => write!(f, "posterize:{}", levels),
+            Filter::Threshold(t) => write!(f, "threshold:{}", t),
+        }
+    }
+}
+
+/// Filters from --filter-chain, applied in order
+#[derive(Debug, Clone)]
+pub struct FilterChain(pub Vec<Filter>);
+
+impl fmt::Display for FilterChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<String> = self.0.iter().map(|step| step.to_string()).collect();
+        write!(f, "{}", steps.join(","))
+    }
+}
+
+impl Serialize for FilterChain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Define the filter stage enum
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterStage {
+    /// Full-resolution image, before resizing
+    Source,
+    /// Image resized to the character grid
+    Resized,
+}
+
+// Define the ink/paper threshold method enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Binarize {
+    /// Fixed luminance level (--binary-level)
+    Global,
+    /// Otsu's method on the luminance histogram
+    Otsu,
+    /// Mean luminance of the surrounding --binary-window cells
+    Adaptive,
+}
+
+// Define the block element mode enum
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blocks {
+    /// 2×2 quadrant blocks (▘▝▀▖▌▞▛…)
+    Quadrant,
+    /// 2×3 sextants from Symbols for Legacy Computing
+    Sextant,
+}
+
+// Define the edge glyph set enum
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeGlyphs {
+    /// Four orientations | / - \ with heavy variants for strong edges
+    Basic,
+    /// 16 polarity-aware orientations plus corners
+    Fine,
 }
 
 /// Command-line arguments for img2ascii
-#[derive(Debug, Parser)]
+#[derive(Debug, Parser, Serialize)]
 #[command(version)]
 pub struct Args {
-    /// Path to the input image file
+    /// Path to the input image file (or previously saved txt/ansi/json art)
     #[arg(value_name = "IMAGE", index = 1)]
     pub image: String,
 
-    /// Output width in characters
-    #[arg(short, long, default_value_t = 80)]
-    pub width: u32,
+    /// How to read the input file
+    #[arg(long, value_enum, default_value_t = InputFormat::Auto)]
+    pub input_format: InputFormat,
+
+    /// Ignore the EXIF orientation tag instead of rotating photos upright
+    #[arg(long)]
+    pub no_exif_rotate: bool,
+
+    /// Crop to x,y,w,h in pixels or percentages (e.g. 10%,0,50%,100%),
+    /// measured after rotation and flipping
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_crop)]
+    pub crop: Option<Crop>,
+
+    /// Rotate clockwise by 90, 180 or 270 degrees
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rotation)]
+    pub rotate: Option<u16>,
+
+    /// Mirror horizontally (h) or vertically (v)
+    #[arg(long, value_enum)]
+    pub flip: Option<Flip>,
+
+    /// Remove uniform borders matching the top-left pixel color
+    #[arg(long)]
+    pub trim: bool,
 
-    /// Output height in characters (overrides aspect ratio)
+    /// Output width in characters (default: 80, or derived from --height)
+    #[arg(short, long)]
+    pub width: Option<u32>,
+
+    /// Output height in characters (derived from the width if omitted)
     #[arg(short = 'H', long)]
     pub height: Option<u32>,
 
+    /// How to fit the image when both --width and --height are given (fit, fill, stretch)
+    #[arg(long, value_enum, default_value_t = Resize::Stretch)]
+    pub resize: Resize,
+
+    /// Size the output as a percentage of the source, one character per pixel at 100
+    #[arg(long, value_name = "PCT", conflicts_with_all = ["width", "height"], value_parser = parse_scale)]
+    pub scale: Option<f32>,
+
     /// Enable colored ASCII output
     #[arg(short, long)]
     pub color: bool,
 
-    /// Output format (txt, html, ansi). If omitted, prints to terminal.
+    /// Quantize colors to a palette: cga, ega, gameboy, pico8, solarized, nord,
+    /// gruvbox, or a .gpl / hex list file
+    #[arg(long, value_name = "NAME|FILE")]
+    pub palette: Option<String>,
+
+    /// Quantize colors to an adaptive palette of N colors built from the image
+    #[arg(long, value_name = "N", conflicts_with = "palette", value_parser = clap::value_parser!(u16).range(1..=256))]
+    pub colors: Option<u16>,
+
+    /// Adaptive palette method for --colors (median-cut, kmeans)
+    #[arg(long, value_enum, default_value_t = ColorMethod::Kmeans)]
+    pub colors_method: ColorMethod,
+
+    /// Color space for nearest palette color (oklab, lab)
+    #[arg(long, value_enum, default_value_t = ColorSpace::Oklab)]
+    pub palette_space: ColorSpace,
+
+    /// Dithering for palette quantization (none, floyd-steinberg, bayer)
+    #[arg(long, value_enum, default_value_t = Dither::None)]
+    pub dither: Dither,
+
+    /// Output format (txt, html, ansi, json). If omitted, prints to terminal.
     #[arg(short = 'o', long = "output", value_enum)]
     pub output: Option<OutputFormat>,
 
+    /// Luminance model for shading, equalization and edges (bt601, bt709, linear, oklab)
+    #[arg(long, value_enum, default_value_t = LuminanceModel::Bt601)]
+    pub luminance: LuminanceModel,
+
+    /// Color transparent pixels are blended onto, as hex (default: white, or
+    /// black with --theme dark)
+    #[arg(long, value_name = "HEX", value_parser = parse_color)]
+    pub background: Option<[u8; 3]>,
+
+    /// Cells with average alpha below this (0-255) become blank, uncolored
+    /// spaces; 0 keeps every cell
+    #[arg(long, default_value_t = 0)]
+    pub alpha_cutoff: u8,
+
+    /// Reverse the brightness-to-character mapping (edges: only --edge-glyphs fine changes)
+    #[arg(long)]
+    pub invert: bool,
+
+    /// Background the art is viewed on (dark implies --invert; also styles HTML)
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+
+    /// Custom characters for shading, in any order; they are sorted by ink
+    /// coverage measured on the embedded font (also the --shape candidates)
+    #[arg(long)]
+    pub charset: Option<String>,
+
+    /// Pick characters by matching glyph shapes against 8x16 source blocks
+    #[arg(long, conflicts_with_all = ["edges", "edge_overlay"])]
+    pub shape: bool,
+
+    /// Block elements with a foreground and background color per cell (quadrant, sextant)
+    #[arg(long, value_enum, conflicts_with_all = ["edges", "edge_overlay", "shape", "charset"])]
+    pub blocks: Option<Blocks>,
+
+    /// Two-tone output: every cell is ink or paper (global, otsu, adaptive)
+    #[arg(long, value_enum, conflicts_with_all = ["edges", "edge_overlay", "shape", "charset", "blocks"])]
+    pub binary: Option<Binarize>,
+
+    /// Luminance level for --binary global; darker cells become ink (0-255)
+    #[arg(long, default_value_t = 128)]
+    pub binary_level: u8,
+
+    /// Neighborhood size in cells for --binary adaptive
+    #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(u32).range(1..))]
+    pub binary_window: u32,
+
+    /// How much darker than its neighborhood a cell must be to become ink (--binary adaptive)
+    #[arg(long, default_value_t = 8.0, allow_negative_numbers = true)]
+    pub binary_offset: f32,
+
+    /// Dithering of ink and paper for --binary (none, floyd-steinberg, bayer)
+    #[arg(long, value_enum, default_value_t = Dither::None)]
+    pub binary_dither: Dither,
+
+    /// Glyph for ink cells in --binary mode
+    #[arg(long, default_value_t = '#')]
+    pub ink: char,
+
+    /// Glyph for paper cells in --binary mode
+    #[arg(long, default_value_t = ' ')]
+    pub paper: char,
+
+    /// Block comparison metric for --shape (mse, ssim)
+    #[arg(long, value_enum, default_value_t = ShapeMetric::Ssim)]
+    pub shape_metric: ShapeMetric,
+
     /// Apply Sobel edge detection before conversion
     #[arg(short = 'e', long)]
     pub edges: bool,
 
-    /// Edge detection threshold (0-255)
+    /// Draw edge characters on top of the shaded art instead of a blank background
+    #[arg(long)]
+    pub edge_overlay: bool,
+
+    /// How aggressively edges win: multiplier on edge magnitude before thresholding
+    #[arg(long, default_value_t = 1.0)]
+    pub edge_weight: f32,
+
+    /// Edge detection threshold (0-255, or "auto" to pick from the image)
+    #[arg(long, default_value_t = EdgeThreshold::Fixed(100), value_parser = parse_edge_threshold)]
+    pub edge_threshold: EdgeThreshold,
+
+    /// Method used by --edge-threshold auto (otsu, density)
+    #[arg(long, value_enum, default_value_t = EdgeAuto::Otsu)]
+    pub edge_auto: EdgeAuto,
+
+    /// Target percentage of edge pixels for --edge-auto density
+    #[arg(long, default_value_t = 10.0)]
+    pub edge_density: f32,
+
+    /// Edge gradient operator (sobel, scharr, prewitt, roberts, log)
+    #[arg(long, value_enum, default_value_t = EdgeOperator::Sobel)]
+    pub edge_operator: EdgeOperator,
+
+    /// Edge kernel size (3 or 5)
+    #[arg(long, default_value_t = 3, value_parser = parse_kernel_size)]
+    pub edge_kernel: u8,
+
+    /// Detect edges at this multiple of the character grid resolution and pool
+    /// them into cells (1 = on the grid, 0 = full source resolution, up to 16)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(0..=16))]
+    pub edge_scale: u32,
+
+    /// Edge glyph set (basic, fine)
+    #[arg(long, value_enum, default_value_t = EdgeGlyphs::Basic)]
+    pub edge_glyphs: EdgeGlyphs,
+
+    /// Custom edge glyphs: 16 characters for gradient directions clockwise from
+    /// "pointing right", plus an optional corner character (implies fine glyphs)
+    #[arg(long, value_parser = parse_glyph_table)]
+    pub edge_glyph_table: Option<String>,
+
+    /// Edge magnitude (0-255) at which heavy glyphs (# X =) replace | / - \
+    #[arg(long, default_value_t = 200)]
+    pub edge_strong: u8,
+
+    /// Edge detection algorithm (sobel, canny)
+    #[arg(long, value_enum, default_value_t = EdgeAlgo::Sobel)]
+    pub edge_algo: EdgeAlgo,
+
+    /// Canny weak edge threshold (0-255)
+    #[arg(long, default_value_t = 50)]
+    pub edge_low: u8,
+
+    /// Canny strong edge threshold (0-255)
     #[arg(long, default_value_t = 100)]
-    pub edge_threshold: u8,
-}  
+    pub edge_high: u8,
+
+    /// Gaussian pre-blur sigma for canny and log (0 disables)
+    #[arg(long, default_value_t = 1.0, value_parser = parse_edge_sigma)]
+    pub edge_sigma: f32,
+
+    /// Filters applied in order, e.g. blur:1.5,sharpen:0.8,posterize:4,threshold:128
+    #[arg(long, value_name = "FILTERS", value_parser = parse_filter_chain)]
+    pub filter_chain: Option<FilterChain>,
+
+    /// Image the filter chain runs on (source, resized)
+    #[arg(long, value_enum, default_value_t = FilterStage::Source)]
+    pub filter_stage: FilterStage,
+
+    /// Equalize the luminance histogram before mapping (global, clahe)
+    #[arg(long, value_enum)]
+    pub equalize: Option<Equalize>,
+
+    /// CLAHE tile size in pixels of the resized image
+    #[arg(long, default_value_t = 8)]
+    pub clahe_tile: u32,
+
+    /// CLAHE clip limit (multiple of the average histogram bin)
+    #[arg(long, default_value_t = 2.0)]
+    pub clahe_clip: f32,
+
+    /// Brightness offset applied before mapping (-1.0 to 1.0)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub brightness: f32,
+
+    /// Contrast multiplier applied before mapping (1.0 = unchanged)
+    #[arg(long, default_value_t = 1.0)]
+    pub contrast: f32,
+
+    /// Gamma correction applied before mapping (> 1.0 brightens midtones)
+    #[arg(long, default_value_t = 1.0)]
+    pub gamma: f32,
 
+    /// Stretch the luminance histogram between --levels-low and --levels-high
+    #[arg(long)]
+    pub auto_levels: bool,
+
+    /// Low percentile mapped to black by --auto-levels
+    #[arg(long, default_value_t = 1.0)]
+    pub levels_low: f32,
+
+    /// High percentile mapped to white by --auto-levels
+    #[arg(long, default_value_t = 99.0)]
+    pub levels_high: f32,
+
+    /// Print processing details to stderr
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Run-length encode rows in JSON output
+    #[arg(long)]
+    pub json_rle: bool,
+}
+
+
+/// Accept only the supported edge kernel sizes
+fn parse_kernel_size(s: &str) -> Result<u8, String> {
+    match s {
+        "3" => Ok(3),
+        "5" => Ok(5),
+        _ => Err(format!("kernel size must be 3 or 5, got '{}'", s)),
+    }
+}
+
+/// Parse an edge threshold: "auto" or a number from 0 to 255
+fn parse_edge_threshold(s: &str) -> Result<EdgeThreshold, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(EdgeThreshold::Auto);
+    }
+    s.parse()
+        .map(EdgeThreshold::Fixed)
+        .map_err(|_| format!("expected 0-255 or 'auto', got '{}'", s))
+}
+
+/// Accept an edge glyph table of 16 or 17 characters
+fn parse_glyph_table(s: &str) -> Result<String, String> {
+    match s.chars().count() {
+        16 | 17 => Ok(s.to_string()),
+        n => Err(format!("expected 16 glyphs (plus an optional corner glyph), got {}", n)),
+    }
+}
+
+/// Parse a hex color such as "#1e1e2e" or "fff"
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    parse_hex_color(s).ok_or_else(|| format!("expected a hex color like '#ffffff', got '{}'", s))
+}
+
+/// Accept a clockwise rotation of 90, 180 or 270 degrees
+fn parse_rotation(s: &str) -> Result<u16, String> {
+    match s {
+        "90" => Ok(90),
+        "180" => Ok(180),
+        "270" => Ok(270),
+        _ => Err(format!("rotation must be 90, 180 or 270, got '{}'", s)),
+    }
+}
+
+/// Accept a scale percentage above 0 and up to 100 (one character per pixel)
+fn parse_scale(s: &str) -> Result<f32, String> {
+    s.parse()
+        .ok()
+        .filter(|pct: &f32| *pct > 0.0 && *pct <= 100.0)
+        .ok_or_else(|| format!("scale must be a percentage above 0 and at most 100, got '{}'", s))
+}
+
+/// Parse a crop rectangle "x,y,w,h" whose parts are pixels or percentages
+fn parse_crop(s: &str) -> Result<Crop, String> {
+    let parts: Vec<Extent> = s
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            match part.strip_suffix('%') {
+                Some(pct) => pct
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|p| (0.0..=100.0).contains(p))
+                    .map(Extent::Percent),
+                None => part.parse().ok().map(Extent::Pixels),
+            }
+            .ok_or_else(|| format!("invalid crop value '{}'", part))
+        })
+        .collect::<Result<_, _>>()?;
+
+    match parts[..] {
+        [x, y, width, height] => Ok(Crop { x, y, width, height }),
+        _ => Err(format!("expected x,y,w,h, got '{}'", s)),
+    }
+}
+
+/// Largest accepted blur sigma, in pixels
+const MAX_SIGMA: f32 = 100.0;
+
+/// Whether a blur sigma is one `image::imageops` accepts (positive, normal)
+/// and within MAX_SIGMA
+fn valid_sigma(sigma: f32) -> bool {
+    sigma.is_normal() && sigma > 0.0 && sigma <= MAX_SIGMA
+}
+
+/// Accept an edge pre-blur sigma of 0 (no blur) or one blur can use
+fn parse_edge_sigma(s: &str) -> Result<f32, String> {
+    s.parse()
+        .ok()
+        .filter(|&sigma: &f32| sigma == 0.0 || valid_sigma(sigma))
+        .ok_or_else(|| format!("sigma must be 0 (no blur) or a positive number up to {}, got '{}'", MAX_SIGMA, s))
+}
+
+/// Parse a comma-separated filter chain of name:value steps
+fn parse_filter_chain(s: &str) -> Result<FilterChain, String> {
+    let steps = s
+        .split(',')
+        .map(|step| {
+            let (name, value) = step
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| format!("expected name:value, got '{}'", step))?;
+            let invalid = || format!("invalid value for {}: '{}'", name, value);
+
+            match name {
+                "blur" => value.parse().ok().filter(|&v| valid_sigma(v)).map(Filter::Blur).ok_or_else(invalid),
+                "sharpen" => value.parse().ok().filter(|&v| valid_sigma(v)).map(Filter::Sharpen).ok_or_else(invalid),
+                "posterize" => value.parse().ok().filter(|&v: &u8| v >= 2).map(Filter::Posterize).ok_or_else(invalid),
+                "threshold" => value.parse().map(Filter::Threshold).map_err(|_| invalid()),
+                _ => Err(format!("unknown filter '{}' (blur, sharpen, posterize, threshold)", name)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(FilterChain(steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_chain_parses_steps_in_order() {
+        let chain = parse_filter_chain("blur:1.5, sharpen:0.8,posterize:4,threshold:128").unwrap();
+        assert_eq!(chain.to_string(), "blur:1.5,sharpen:0.8,posterize:4,threshold:128");
+    }
+
+    #[test]
+    fn filter_chain_rejects_invalid_steps() {
+        for chain in ["", "blur", "blur:0", "sharpen:-1", "posterize:1", "threshold:256", "emboss:1", "blur:1,"] {
+            assert!(parse_filter_chain(chain).is_err(), "{:?} was accepted", chain);
+        }
+    }
+
+    #[test]
+    fn filter_chain_rejects_sigmas_blur_cannot_use() {
+        for chain in ["blur:inf", "sharpen:inf", "blur:NaN", "blur:1e-45", "sharpen:101"] {
+            assert!(parse_filter_chain(chain).is_err(), "{:?} was accepted", chain);
+        }
+    }
+
+    #[test]
+    fn edge_sigma_is_zero_or_usable_by_blur() {
+        assert_eq!(parse_edge_sigma("0"), Ok(0.0));
+        assert_eq!(parse_edge_sigma("1.4"), Ok(1.4));
+        for sigma in ["-1", "inf", "NaN", "1e-45", "101"] {
+            assert!(parse_edge_sigma(sigma).is_err(), "{:?} was accepted", sigma);
+        }
+    }
+
+    #[test]
+    fn scale_must_be_a_positive_percentage() {
+        assert_eq!(parse_scale("50"), Ok(50.0));
+        assert_eq!(parse_scale("100"), Ok(100.0));
+        for scale in ["0", "-5", "NaN", "inf", "101", "abc"] {
+            assert!(parse_scale(scale).is_err(), "{:?} was accepted", scale);
+        }
+    }
+}
@@ -5,40 +5,170 @@
 //! preserving color information for colored output.
 
 use image::GenericImageView;
-use crate::types::{AsciiCell, ASCII_CHARS, EDGE_ASCII_CHARS, calculate_luminance};
+use std::f32::consts::PI;
+
+use crate::ramp::Ramp;
+use crate::types::{AsciiCell, EdgeMap, LuminanceModel, ASCII_CHARS, EDGE_ASCII_CHARS, STRONG_EDGE_ASCII_CHARS, calculate_luminance};
 
 /// Converts an image into a 2D grid of ASCII cells
-pub fn generate_ascii(img: &image::DynamicImage) -> Vec<Vec<AsciiCell>> {
-    generate_ascii_with_charset(img, ASCII_CHARS)
+///
+/// `ASCII_CHARS` runs from the densest glyph for dark pixels to a blank for
+/// light ones, which reads as dark ink on a light background. With `invert`
+/// the mapping is reversed for light text on a dark background.
+pub fn generate_ascii(img: &image::DynamicImage, invert: bool, luminance: &LuminanceModel) -> Vec<Vec<AsciiCell>> {
+    generate_ascii_with_charset(img, ASCII_CHARS, invert, luminance)
 }
 
-/// Converts edge-detected image to ASCII using directional edge characters
-pub fn generate_ascii_edges(img: &image::DynamicImage) -> Vec<Vec<AsciiCell>> {
+/// Converts an edge map to ASCII using directional edge characters
+///
+/// Without a glyph table the basic set is used: edges at or above `strong`
+/// magnitude use the heavy glyphs (`STRONG_EDGE_ASCII_CHARS`), weaker ones
+/// the light set. With a table (see `FINE_EDGE_GLYPHS`) the gradient
+/// direction picks one of 16 glyphs, and a 17th entry, if present, marks
+/// corners and junctions. Colors are scaled by magnitude so faint edges are
+/// also dimmer.
+pub fn generate_ascii_edges(edges: &EdgeMap, strong: u8, table: Option<&[char]>) -> Vec<Vec<AsciiCell>> {
+    let mut rows = Vec::with_capacity(edges.height as usize);
+
+    for y in 0..edges.height {
+        let mut row = Vec::with_capacity(edges.width as usize);
+
+        for x in 0..edges.width {
+            let edge = edges.get(x, y);
+
+            if !edge.is_edge {
+                // No edge - use space
+                row.push(AsciiCell::new(' ', edge.r, edge.g, edge.b));
+                continue;
+            }
+
+            // Edge detected - use direction to pick character
+            let ch = match table {
+                Some(table) => match table.get(16) {
+                    Some(&corner) if is_corner(edges, x, y) => corner,
+                    _ => table[sector_16(edge.angle)],
+                },
+                None => {
+                    let idx = direction_index(edge.angle);
+                    let ch = if edge.magnitude >= strong as f32 {
+                        STRONG_EDGE_ASCII_CHARS[idx]
+                    } else {
+                        EDGE_ASCII_CHARS[idx]
+                    };
+                    ch as char
+                }
+            };
+
+            // Keep at least half the brightness so weak edges stay visible
+            let scale = 0.5 + 0.5 * edge.magnitude / 255.0;
+            let [r, g, b] = [edge.r, edge.g, edge.b].map(|c| (c as f32 * scale) as u8);
+
+            row.push(AsciiCell::new(ch, r, g, b));
+        }
+
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// Map a gradient direction to an index into EDGE_ASCII_CHARS
+/// Divides 360 degrees into 8 sectors; opposite sectors share a character
+fn direction_index(angle: f32) -> usize {
+    // Normalize angle to 0-2π
+    let normalized = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+
+    // Divide into 8 sectors (45 degrees each) of the gradient direction,
+    // with y pointing down. The edge line runs perpendicular to it.
+    // 0: → (gradient right, vertical edge)
+    // 1: ↘ (gradient down-right)
+    // 2: ↓ (gradient down, horizontal edge)
+    // 3: ↙ (gradient down-left)
+    // 4-7: the same, reversed
+    let sector = ((normalized / (PI / 4.0) + 0.5) as u8) % 8;
+
+    // Map to character indices in EDGE_ASCII_CHARS = "|/-\\+*. "
+    match sector {
+        0 | 4 => 0, // | vertical
+        1 | 5 => 1, // / diagonal
+        2 | 6 => 2, // - horizontal
+        _ => 3,     // \ diagonal
+    }
+}
+
+/// Map a gradient direction to one of 16 sectors of 22.5 degrees,
+/// starting at "gradient points right" and turning clockwise on screen
+fn sector_16(angle: f32) -> usize {
+    let normalized = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+    ((normalized / (PI / 8.0) + 0.5) as usize) % 16
+}
+
+/// A corner or junction: several edges around (x, y) whose line
+/// orientations disagree
+///
+/// Orientations are compared as doubled angles so opposite gradients count
+/// as the same line; the magnitude-weighted mean of those vectors is short
+/// when the neighbourhood mixes orientations.
+fn is_corner(edges: &EdgeMap, x: u32, y: u32) -> bool {
+    let mut count = 0;
+    let (mut sum_x, mut sum_y, mut total) = (0.0, 0.0, 0.0);
+
+    for ny in y.saturating_sub(1)..=(y + 1).min(edges.height - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(edges.width - 1) {
+            let p = edges.get(nx, ny);
+            if !p.is_edge {
+                continue;
+            }
+            count += 1;
+            sum_x += p.magnitude * (2.0 * p.angle).cos();
+            sum_y += p.magnitude * (2.0 * p.angle).sin();
+            total += p.magnitude;
+        }
+    }
+
+    // Below 0.5 the orientations spread over more than about 60 degrees
+    count >= 3 && total > 0.0 && f32::hypot(sum_x, sum_y) / total < 0.5
+}
+
+/// Overlays edge characters onto shaded ASCII art
+///
+/// Every cell where `edges` holds an edge character replaces the shaded
+/// cell, giving outlined-but-shaded art. Both grids must come from the same
+/// resized image.
+pub fn overlay_edges(mut shaded: Vec<Vec<AsciiCell>>, edges: &[Vec<AsciiCell>]) -> Vec<Vec<AsciiCell>> {
+    for (shaded_row, edge_row) in shaded.iter_mut().zip(edges) {
+        for (cell, edge) in shaded_row.iter_mut().zip(edge_row) {
+            if edge.ch != ' ' {
+                *cell = *edge;
+            }
+        }
+    }
+
+    shaded
+}
+
+/// Converts an image into ASCII cells using a measured character ramp
+///
+/// Unlike `generate_ascii`, each pixel takes the character whose measured
+/// darkness is nearest, so unevenly spaced charsets still shade smoothly.
+pub fn generate_ascii_with_ramp(
+    img: &image::DynamicImage,
+    ramp: &Ramp,
+    invert: bool,
+    luminance: &LuminanceModel,
+) -> Vec<Vec<AsciiCell>> {
     let mut rows = Vec::with_capacity(img.height() as usize);
 
     for y in 0..img.height() {
         let mut row = Vec::with_capacity(img.width() as usize);
 
         for x in 0..img.width() {
-            let [r, g, b, dir_encoded] = img.get_pixel(x, y).0;
+            let [r, g, b, _] = img.get_pixel(x, y).0;
 
-            // Check if it's an edge (non-black)
-            if r == 0 && g == 0 && b == 0 {
-                // No edge - use space
-                row.push(AsciiCell {
-                    ch: ' ',
-                    r, g, b,
-                });
-            } else {
-                // Edge detected - use direction to pick character
-                // dir_encoded contains the character index
-                let idx = (dir_encoded as usize).min(EDGE_ASCII_CHARS.len() - 1);
-                
-                row.push(AsciiCell {
-                    ch: EDGE_ASCII_CHARS[idx] as char,
-                    r, g, b,
-                });
-            }
+            let brightness = calculate_luminance(r, g, b, luminance) as u8;
+            let darkness = if invert { brightness } else { 255 - brightness };
+
+            row.push(AsciiCell::new(ramp.pick(darkness), r, g, b));
         }
 
         rows.push(row);
@@ -48,7 +178,12 @@ pub fn generate_ascii_edges(img: &image::DynamicImage) -> Vec<Vec<AsciiCell>> {
 }
 
 /// Internal function to generate ASCII with a specific character set
-fn generate_ascii_with_charset(img: &image::DynamicImage, charset: &[u8]) -> Vec<Vec<AsciiCell>> {
+fn generate_ascii_with_charset(
+    img: &image::DynamicImage,
+    charset: &[u8],
+    invert: bool,
+    luminance: &LuminanceModel,
+) -> Vec<Vec<AsciiCell>> {
     let mut rows = Vec::with_capacity(img.height() as usize);
 
     for y in 0..img.height() {
@@ -58,17 +193,15 @@ fn generate_ascii_with_charset(img: &image::DynamicImage, charset: &[u8]) -> Vec
             let [r, g, b, _] = img.get_pixel(x, y).0;
 
             // brightness (luminosity)
-            let brightness = calculate_luminance(r, g, b) as u8;
+            let mut brightness = calculate_luminance(r, g, b, luminance) as u8;
+            if invert {
+                brightness = 255 - brightness;
+            }
 
             let idx = (brightness as usize * charset.len()) / 256;
             let idx = idx.min(charset.len() - 1);
 
-            row.push(AsciiCell {
-                ch: charset[idx] as char,
-                r,
-                g,
-                b,
-            });
+            row.push(AsciiCell::new(charset[idx] as char, r, g, b));
         }
 
         rows.push(row);
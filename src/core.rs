@@ -3,43 +3,335 @@
 //! Contains the main run() function that orchestrates the entire image-to-ASCII
 //! conversion process: parsing arguments, loading the image, resizing it,
 //! converting to ASCII, and outputting in the requested format.
+//! Previously saved art (txt, ansi, json) can be loaded instead of an image
+//! and re-rendered in another format.
 
-use clap::Parser;
-use image::{GenericImageView, ImageError};
+use clap::{Parser, ValueEnum};
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageError, ImageReader};
 use std::io::ErrorKind;
+use std::path::Path;
 use image::imageops::FilterType;
+use image::metadata::Orientation;
 
-use crate::cli::{Args, OutputFormat};
+use crate::alpha::{blank_transparent, flatten_alpha};
+use crate::binary::{generate_ascii_binary, BinaryOptions};
+use crate::blocks::{block_charset, generate_blocks, sub_cells};
+use crate::adjust::{adjust_tone, luminance_percentiles, Tone};
+use crate::cli::{Args, Resize, EdgeAlgo, EdgeAuto, EdgeGlyphs, EdgeOperator, EdgeThreshold, Equalize, FilterStage, InputFormat, OutputFormat, Theme};
 use crate::output::output_filename;
-use crate::convert::{generate_ascii, generate_ascii_edges};
+use crate::convert::{generate_ascii, generate_ascii_edges, generate_ascii_with_ramp, overlay_edges};
+use crate::parse::{parse_ansi, parse_json, parse_text};
 use crate::renderansi::render_ansi;
 use crate::renderhtml::render_html;
-use crate::edge::sobel_edge_detection;
+use crate::renderjson::render_json;
+use crate::edge::{
+    canny_edge_detection, gradient_edge_detection, log_edge_detection, pool_edges, EdgeOptions, Threshold,
+};
+use crate::equalize::{clahe, equalize_histogram};
+use crate::filter::apply_filters;
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH, PRINTABLE_ASCII};
+use crate::palette::{builtin_palette, extract_palette, parse_gpl, parse_hex_list, quantize_cells, BUILTIN_PALETTES};
+use crate::ramp::Ramp;
+use crate::shape::generate_ascii_by_shape;
+use crate::transform::{crop, flip, rotate, trim};
+use crate::types::{AsciiCell, EdgeMap, ASCII_CHARS, EDGE_ASCII_CHARS, FINE_EDGE_GLYPHS, STRONG_EDGE_ASCII_CHARS};
+
+/// Output width in characters when neither --width nor --height is given
+const DEFAULT_WIDTH: u32 = 80;
+
+/// Correction factor for character aspect ratio (terminal characters are taller than wide)
+const CHAR_ASPECT: f32 = 0.43;
 
 // Main logic function
 pub fn run() -> std::io::Result<()> {
     // Parse command-line arguments into Args struct using clap
     let args = Args::parse();
 
+    let input_format = resolve_input_format(&args);
+
+    let mut ascii_cells = if input_format == InputFormat::Image {
+        convert_image(&args)
+    } else {
+        load_art(&args, &input_format)
+    };
+
+    // Fixed or adaptive palette, remapping every cell color to it
+    let palette = if let Some(spec) = &args.palette {
+        Some(load_palette(spec))
+    } else {
+        args.colors.map(|n| extract_palette(&ascii_cells, n as usize, &args.colors_method))
+    };
+
+    if let Some(palette) = &palette {
+        if args.verbose {
+            eprintln!(
+                "Palette: {} ({} colors), {} distance, dither={}",
+                args.palette.as_deref().unwrap_or(&value_name(&args.colors_method)),
+                palette.len(), value_name(&args.palette_space), value_name(&args.dither),
+            );
+        }
+        quantize_cells(&mut ascii_cells, palette, &args.palette_space, &args.dither);
+        if args.verbose {
+            print_palette_legend(&ascii_cells, palette);
+        }
+    }
+
+    let terminal_text = render_ansi(&ascii_cells, args.color);
+
+    // 1. Print to terminal (ALWAYS)
+    print!("{}", terminal_text);
+
+    // 2. Optionally save to file
+    if let Some(format) = &args.output {
+        let filename = output_filename(&args.image, format);
+
+        // Re-rendering art into its own format would overwrite the input
+        if let (Ok(out), Ok(input)) = (std::fs::canonicalize(&filename), std::fs::canonicalize(&args.image)) {
+            if out == input {
+                eprintln!("Error: Refusing to overwrite input file: {}", args.image);
+                std::process::exit(5);
+            }
+        }
+
+        match format {
+            OutputFormat::Html => {
+                let html = render_html(&ascii_cells, args.theme, palette.as_deref());
+                std::fs::write(&filename, html)?;
+            }
+
+            OutputFormat::Ansi => {
+                std::fs::write(&filename, terminal_text)?;
+            }
+
+            OutputFormat::Txt => {
+                let plain = render_ansi(&ascii_cells, false);
+                std::fs::write(&filename, plain)?;
+            }
+
+            OutputFormat::Json => {
+                let charset = if input_format != InputFormat::Image {
+                    charset_of(&ascii_cells)
+                } else if args.edge_overlay {
+                    // The shading ramp, with edge glyphs drawn over it
+                    let mut charset = shade_charset(&args);
+                    push_distinct(&mut charset, edge_charset(&args).chars());
+                    charset
+                } else if args.edges {
+                    edge_charset(&args)
+                } else if args.shape {
+                    args.charset.clone().unwrap_or_else(|| PRINTABLE_ASCII.to_string())
+                } else if let Some(mode) = &args.blocks {
+                    block_charset(mode)
+                } else if args.binary.is_some() {
+                    [args.ink, args.paper].iter().collect()
+                } else {
+                    shade_charset(&args)
+                };
+                let json = render_json(&ascii_cells, &args, &charset, args.json_rle);
+                std::fs::write(&filename, json)?;
+            }
+        }
+
+        eprintln!("Saved output to {}", filename);
+    }
+
+    Ok(())
+}
+
+/// Command-line spelling of an enum value, for verbose output
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
+
+/// Print each palette color with a swatch and its share of cells to stderr
+fn print_palette_legend(cells: &[Vec<AsciiCell>], palette: &[[u8; 3]]) {
+    // Blank (letterbox or transparent) cells carry no palette color
+    let colored: Vec<&AsciiCell> = cells.iter().flatten().filter(|c| !c.blank).collect();
+    let total = colored.len().max(1);
+
+    for (i, &[r, g, b]) in palette.iter().enumerate() {
+        let used = colored.iter().filter(|c| [c.r, c.g, c.b] == [r, g, b]).count();
+        eprintln!(
+            "  c{:<3} \x1b[48;2;{};{};{}m    \x1b[0m #{:02x}{:02x}{:02x} {:5.1}%",
+            i, r, g, b, r, g, b, used as f32 * 100.0 / total as f32,
+        );
+    }
+}
+
+/// Apply --rotate, --flip, --trim and --crop in that order
+fn apply_geometry(args: &Args, mut img: DynamicImage) -> DynamicImage {
+    if let Some(degrees) = args.rotate {
+        img = rotate(img, degrees);
+    }
+    if let Some(direction) = &args.flip {
+        img = flip(img, direction);
+    }
+    if args.trim {
+        let (w, h) = img.dimensions();
+        img = trim(img);
+        if args.verbose {
+            eprintln!("Trim: {}x{} px -> {}x{} px", w, h, img.width(), img.height());
+        }
+    }
+    if let Some(rect) = &args.crop {
+        img = match crop(&img, rect) {
+            Some(cropped) => cropped,
+            None => {
+                eprintln!(
+                    "Error: Crop {} is outside the {}x{} image",
+                    rect, img.width(), img.height(),
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+
+    img
+}
+
+/// Decode the input image, applying its EXIF orientation unless disabled
+fn open_image(args: &Args) -> Result<DynamicImage, ImageError> {
+    let mut decoder = ImageReader::open(&args.image)?.into_decoder()?;
+    // Formats without orientation metadata report no transform
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder)?;
+
+    if orientation != Orientation::NoTransforms {
+        if args.no_exif_rotate {
+            if args.verbose {
+                eprintln!("EXIF orientation: {:?} (ignored)", orientation);
+            }
+        } else {
+            if args.verbose {
+                eprintln!("EXIF orientation: {:?}", orientation);
+            }
+            img.apply_orientation(orientation);
+        }
+    }
+
+    Ok(img)
+}
+
+/// Resolve --palette to a built-in palette or read it from a file
+fn load_palette(spec: &str) -> Vec<[u8; 3]> {
+    if let Some(palette) = builtin_palette(spec) {
+        return palette;
+    }
+
+    let text = match std::fs::read_to_string(spec) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("Error: Unknown palette '{}' (built-in: {})", spec, BUILTIN_PALETTES.join(", "));
+            std::process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Failed to read palette '{}': {}", spec, e);
+            std::process::exit(1);
+        }
+    };
+
+    let parsed = if text.trim_start().starts_with("GIMP Palette") {
+        parse_gpl(&text)
+    } else {
+        parse_hex_list(&text)
+    };
+
+    match parsed {
+        Ok(palette) if !palette.is_empty() => palette,
+        Ok(_) => {
+            eprintln!("Error: Palette '{}' has no colors", spec);
+            std::process::exit(3);
+        }
+        Err(e) => {
+            eprintln!("Error: Invalid palette '{}': {}", spec, e);
+            std::process::exit(3);
+        }
+    }
+}
+
+/// Resolve `--input-format auto` from the file extension
+fn resolve_input_format(args: &Args) -> InputFormat {
+    if args.input_format != InputFormat::Auto {
+        return args.input_format.clone();
+    }
+
+    let ext = Path::new(&args.image)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("txt") => InputFormat::Txt,
+        Some("ansi") => InputFormat::Ansi,
+        Some("json") => InputFormat::Json,
+        _ => InputFormat::Image,
+    }
+}
+
+/// Load previously produced art back into a cell grid
+fn load_art(args: &Args, format: &InputFormat) -> Vec<Vec<AsciiCell>> {
+    let text = match std::fs::read_to_string(&args.image) {
+        Ok(text) => text,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("Error: File not found: {}", args.image);
+            std::process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Failed to read '{}': {}", args.image, e);
+            std::process::exit(1);
+        }
+    };
+
+    match format {
+        InputFormat::Txt => parse_text(&text),
+        InputFormat::Ansi => parse_ansi(&text),
+        InputFormat::Json => match parse_json(&text) {
+            Ok(cells) => cells,
+            Err(e) => {
+                eprintln!("Error: Invalid JSON art '{}': {}", args.image, e);
+                std::process::exit(3);
+            }
+        },
+        InputFormat::Auto | InputFormat::Image => unreachable!("not an art format"),
+    }
+}
+
+/// Distinct characters of a grid in order of first appearance
+fn charset_of(cells: &[Vec<AsciiCell>]) -> String {
+    let mut charset = String::new();
+    for cell in cells.iter().flatten() {
+        if !charset.contains(cell.ch) {
+            charset.push(cell.ch);
+        }
+    }
+    charset
+}
+
+/// Load, resize and convert the input image into a cell grid
+fn convert_image(args: &Args) -> Vec<Vec<AsciiCell>> {
     // Try to open the image file, handle errors for file not found + unsupported format
     // don’t define Unsupported anywhere.
-    // It’s defined by the image crate and returned by image::open() when it can’t decode the file.
-    let img = match image::open(&args.image) {
+    // It’s defined by the image crate and returned by ImageReader::into_decoder() in open_image
+    // when it can’t decode the file.
+    let img = match open_image(args) {
         Ok(img) => img,
         Err(ImageError::IoError(ref e)) if e.kind() == ErrorKind::NotFound => {
-        eprintln!("Error: File not found: {}", args.image);
-        std::process::exit(2);
-    }
+            eprintln!("Error: File not found: {}", args.image);
+            std::process::exit(2);
+        }
         Err(ImageError::Unsupported(_)) => {
-        eprintln!("Error: Unsupported image format: {}", args.image);
-        std::process::exit(3);
-    }
+            eprintln!("Error: Unsupported image format: {}", args.image);
+            std::process::exit(3);
+        }
         Err(e) => {
-        eprintln!("Failed to open image '{}': {}", args.image, e);
-        std::process::exit(1);
-    }
+            eprintln!("Failed to open image '{}': {}", args.image, e);
+            std::process::exit(1);
+        }
     };
 
+    // Rotate, flip, trim and crop before the aspect ratio is taken
+    let img = apply_geometry(args, img);
+
     // Get image dimensions (width, height)
     let (w, h) = img.dimensions();
     // Check for zero width or height
@@ -48,72 +340,425 @@ pub fn run() -> std::io::Result<()> {
         std::process::exit(4);
     }
 
-    // Convert dimensions to f32 for aspect ratio calculation
-    let w = w as f32;
-    let h = h as f32;
-
-    let aspect_ratio = h as f32 / w as f32;
+    // Blend transparent pixels onto the background so neither the ramp nor
+    // the edge detector sees the RGB hidden under them
+    let has_alpha = img.color().has_alpha();
+    let img = if has_alpha {
+        let paper = if args.theme == Some(Theme::Dark) { [0, 0, 0] } else { [255, 255, 255] };
+        flatten_alpha(&img, args.background.unwrap_or(paper))
+    } else {
+        img
+    };
 
-    let new_w = args.width;
+    let img = filter_stage(args, img, FilterStage::Source);
 
-    // Correction factor for character aspect ratio (terminal characters are taller than wide)
-    let char_aspect = 0.43;
-    // Calculate output height in characters, correcting for char aspect
-    let mut new_h = (new_w as f32 * aspect_ratio * char_aspect) as u32;
+    // Work out the grid size; --resize fill crops the image to the grid's shape
+    let (img, new_w, new_h, letterbox_size) = plan_size(args, img);
 
-    // If user provided a custom height, override calculated height
-    if let Some(h) = args.height {
-        new_h = h;
+    if args.verbose {
+        eprintln!("Source: {}x{} px, output: {}x{} chars", w, h, new_w, new_h);
+        if let Some((cols, rows)) = letterbox_size {
+            eprintln!("Letterbox: centered in {}x{} chars", cols, rows);
+        }
     }
 
-    // Resize the image to the desired character dimensions using nearest neighbor
-    let resized_img = img.resize_exact(
-        new_w, new_h, FilterType::Nearest,
-    );
-    
-    // Apply edge detection AFTER resize if requested
-    let processed_img = if args.edges {
-        sobel_edge_detection(&resized_img, args.edge_threshold)
+    // Resize the image to the desired character dimensions using nearest neighbor,
+    // or to one glyph-sized block per character when matching shapes, or to
+    // one pixel per sub-cell for block elements
+    let resized_img = if args.shape {
+        img.resize_exact(
+            new_w * GLYPH_WIDTH as u32, new_h * GLYPH_HEIGHT as u32, FilterType::Triangle,
+        )
+    } else if let Some(mode) = &args.blocks {
+        let (sub_cols, sub_rows) = sub_cells(mode);
+        img.resize_exact(
+            new_w * sub_cols, new_h * sub_rows, FilterType::Triangle,
+        )
     } else {
-        resized_img
+        img.resize_exact(
+            new_w, new_h, FilterType::Nearest,
+        )
     };
-    
-    // Convert to ASCII using appropriate character set
-    let ascii_cells = if args.edges {
-        generate_ascii_edges(&processed_img)
+
+    let resized_img = filter_stage(args, resized_img, FilterStage::Resized);
+
+    // Equalization and tone adjustments happen on the resized image, before character selection
+    let resized_img = equalize_and_tone(args, resized_img, args.clahe_tile, args.verbose);
+
+    // A dark background needs light glyphs where the image is bright
+    let invert = args.invert ^ (args.theme == Some(Theme::Dark));
+
+    // Apply edge detection AFTER resize if requested, and convert to ASCII
+    // using the appropriate character set
+    let glyph_table = edge_glyph_table(args);
+    let glyph_table = glyph_table.as_deref();
+
+    let mut cells = if args.edge_overlay {
+        // Shaded ramp everywhere, directional edge characters on top
+        let shaded = shade(args, &resized_img, invert);
+        let edge_map = edge_map_for_grid(args, &img, &resized_img, invert);
+        overlay_edges(shaded, &generate_ascii_edges(&edge_map, args.edge_strong, glyph_table))
+    } else if args.edges {
+        let edge_map = edge_map_for_grid(args, &img, &resized_img, invert);
+        generate_ascii_edges(&edge_map, args.edge_strong, glyph_table)
+    } else if args.shape {
+        let charset: Vec<char> = args.charset.as_deref().unwrap_or(PRINTABLE_ASCII).chars().collect();
+        if args.verbose {
+            eprintln!("Shape: {} over {} glyphs", value_name(&args.shape_metric), charset.len());
+        }
+        match generate_ascii_by_shape(&resized_img, &charset, &args.shape_metric, invert, &args.luminance) {
+            Some(cells) => cells,
+            None => {
+                eprintln!("Error: No character in the charset has a font glyph");
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(mode) = &args.blocks {
+        generate_blocks(&resized_img, mode, invert, &args.luminance)
+    } else if let Some(method) = &args.binary {
+        let options = BinaryOptions {
+            method: method.clone(),
+            level: args.binary_level,
+            window: args.binary_window,
+            offset: args.binary_offset,
+            dither: args.binary_dither.clone(),
+            ink: args.ink,
+            paper: args.paper,
+            invert,
+            luminance: args.luminance.clone(),
+        };
+        let (cells, threshold) = generate_ascii_binary(&resized_img, &options);
+        if args.verbose {
+            match threshold {
+                Some(t) => eprintln!("Binary: {} threshold {} (darkness)", value_name(method), t),
+                None => eprintln!("Binary: adaptive over {} cells, offset {}", args.binary_window, args.binary_offset),
+            }
+        }
+        cells
     } else {
-        generate_ascii(&processed_img)
+        shade(args, &resized_img, invert)
     };
 
-let terminal_text = render_ansi(&ascii_cells, args.color);
+    if has_alpha && args.alpha_cutoff > 0 {
+        // Average alpha over each cell's footprint
+        let alpha = img.resize_exact(new_w, new_h, FilterType::Triangle);
+        blank_transparent(&mut cells, &alpha, args.alpha_cutoff);
+    }
 
-// 1. Print to terminal (ALWAYS)
-print!("{}", terminal_text);
-// eprintln!("DEBUG → color flag = {}", args.color);
+    match letterbox_size {
+        Some((cols, rows)) => letterbox(cells, cols, rows),
+        None => cells,
+    }
+}
 
-// 2. Optionally save to file
-if let Some(format) = &args.output {
-    let filename = output_filename(&args.image, format);
+/// Map brightness to the --charset ramp, or to the default characters
+fn shade(args: &Args, img: &DynamicImage, invert: bool) -> Vec<Vec<AsciiCell>> {
+    match &args.charset {
+        Some(charset) => generate_ascii_with_ramp(img, &build_ramp(args, charset), invert, &args.luminance),
+        None => generate_ascii(img, invert, &args.luminance),
+    }
+}
 
-    match format {
-        OutputFormat::Html => {
-            let html = render_html(&ascii_cells);
-            std::fs::write(&filename, html)?;
+/// Glyphs `shade` can emit: the --charset ramp or the default one
+fn shade_charset(args: &Args) -> String {
+    match &args.charset {
+        Some(charset) => Ramp::from_charset(charset).map(|ramp| ramp.chars.into_iter().collect()).unwrap_or_default(),
+        None => String::from_utf8_lossy(ASCII_CHARS).into_owned(),
+    }
+}
+
+/// Run --filter-chain if it is configured for `stage`
+fn filter_stage(args: &Args, img: DynamicImage, stage: FilterStage) -> DynamicImage {
+    match &args.filter_chain {
+        Some(chain) if args.filter_stage == stage => {
+            if args.verbose {
+                eprintln!("Filters ({}): {}", value_name(&stage), chain);
+            }
+            apply_filters(img, &chain.0, &args.luminance)
         }
+        _ => img,
+    }
+}
+
+/// Work out the output size in characters from --width, --height, --scale and --resize
+///
+/// # Returns
+/// The image to convert (cropped to the grid's shape for `--resize fill`), its
+/// size in characters, and the full grid size when `--resize fit` letterboxes it
+fn plan_size(args: &Args, img: DynamicImage) -> (DynamicImage, u32, u32, Option<(u32, u32)>) {
+    let (w, h) = img.dimensions();
+
+    // Rows per column, correcting for the character aspect ratio
+    let rows_per_col = h as f32 / w as f32 * CHAR_ASPECT;
+    let rows_for = |cols: u32| ((cols as f32 * rows_per_col) as u32).max(1);
+    let cols_for = |rows: u32| ((rows as f32 / rows_per_col) as u32).max(1);
+
+    if matches!(args.resize, Resize::Fit | Resize::Fill) && (args.width.is_none() || args.height.is_none()) {
+        eprintln!("Warning: --resize {} needs both --width and --height, ignoring it", value_name(&args.resize));
+    }
 
-        OutputFormat::Ansi => {
-            std::fs::write(&filename, terminal_text)?;
+    match (args.scale, args.width, args.height) {
+        // One character per source pixel at 100%
+        (Some(pct), _, _) => {
+            let cols = ((w as f32 * pct / 100.0) as u32).max(1);
+            (img, cols, rows_for(cols), None)
         }
+        (None, None, None) => (img, DEFAULT_WIDTH, rows_for(DEFAULT_WIDTH), None),
+        (None, Some(cols), None) => (img, cols, rows_for(cols), None),
+        (None, None, Some(rows)) => (img, cols_for(rows), rows, None),
+        (None, Some(cols), Some(rows)) => match args.resize {
+            Resize::Stretch => (img, cols, rows, None),
+            Resize::Fit => {
+                let (fit_cols, fit_rows) = if rows_for(cols) <= rows {
+                    (cols, rows_for(cols))
+                } else {
+                    (cols_for(rows).min(cols), rows)
+                };
+                (img, fit_cols, fit_rows, Some((cols, rows)))
+            }
+            Resize::Fill => {
+                // Height / width of the grid in source pixel terms
+                let grid_ratio = rows as f32 / (cols as f32 * CHAR_ASPECT);
+                let (crop_w, crop_h) = if (h as f32 / w as f32) > grid_ratio {
+                    (w, ((w as f32 * grid_ratio).round() as u32).clamp(1, h))
+                } else {
+                    (((h as f32 / grid_ratio).round() as u32).clamp(1, w), h)
+                };
+                let cropped = img.crop_imm((w - crop_w) / 2, (h - crop_h) / 2, crop_w, crop_h);
+                (cropped, cols, rows, None)
+            }
+        },
+    }
+}
+
+/// Center a cell grid in a larger grid padded with blank cells
+fn letterbox(cells: Vec<Vec<AsciiCell>>, cols: u32, rows: u32) -> Vec<Vec<AsciiCell>> {
+    let (cols, rows) = (cols as usize, rows as usize);
+    let top = rows.saturating_sub(cells.len()) / 2;
+    let left = cols.saturating_sub(cells.first().map_or(0, |row| row.len())) / 2;
 
-        OutputFormat::Txt => {
-            let plain = render_ansi(&ascii_cells, false);
-            std::fs::write(&filename, plain)?;
+    let mut grid = vec![vec![AsciiCell::blank(); cols]; rows];
+    for (y, row) in cells.into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            if let Some(slot) = grid.get_mut(top + y).and_then(|r| r.get_mut(left + x)) {
+                *slot = cell;
+            }
         }
     }
 
-    eprintln!("Saved output to {}", filename);
+    grid
 }
 
-Ok(())
+/// Measure a custom charset, reporting characters that cannot be used
+fn build_ramp(args: &Args, charset: &str) -> Ramp {
+    let ramp = match Ramp::from_charset(charset) {
+        Some(ramp) => ramp,
+        None => {
+            eprintln!("Error: --charset needs at least two characters with different ink coverage");
+            std::process::exit(1);
+        }
+    };
+
+    for ch in &ramp.missing {
+        eprintln!("Warning: No font glyph for {:?}, skipping it", ch);
+    }
+    for (dropped, kept) in &ramp.duplicates {
+        eprintln!("Warning: {:?} has the same ink coverage as {:?}, skipping it", dropped, kept);
+    }
+
+    if args.verbose {
+        let levels: Vec<String> = ramp
+            .chars
+            .iter()
+            .zip(&ramp.levels)
+            .map(|(ch, level)| format!("{}={:.2}", ch, level))
+            .collect();
+        eprintln!("Ramp: {}", levels.join(" "));
+    }
 
+    ramp
+}
+
+/// Equalize and tone-adjust an image the way the command line asks
+///
+/// `clahe_tile` is the CLAHE tile size in pixels of `img`, so images at a
+/// different resolution than the grid can keep the same tile coverage.
+/// Settings are printed with --verbose only when `report` is set.
+fn equalize_and_tone(args: &Args, img: DynamicImage, clahe_tile: u32, report: bool) -> DynamicImage {
+    let img = match args.equalize {
+        Some(Equalize::Global) => {
+            if report {
+                eprintln!("Equalize: global histogram");
+            }
+            equalize_histogram(&img, &args.luminance)
+        }
+        Some(Equalize::Clahe) => {
+            if report {
+                eprintln!("Equalize: CLAHE tile={}px clip={:.2}", clahe_tile, args.clahe_clip);
+            }
+            clahe(&img, clahe_tile, args.clahe_clip, &args.luminance)
+        }
+        None => img,
+    };
+    apply_tone(args, img, report)
+}
+
+/// Apply brightness, contrast, gamma and auto-levels from the command line
+fn apply_tone(args: &Args, img: DynamicImage, report: bool) -> DynamicImage {
+    let valid_tone = args.gamma.is_finite() && args.gamma > 0.0 && args.contrast.is_finite() && args.contrast >= 0.0;
+    if !valid_tone {
+        eprintln!("Error: --gamma must be a finite positive number and --contrast a finite non-negative one");
+        std::process::exit(1);
+    }
+    if args.levels_low.is_nan() || args.levels_high.is_nan() || args.levels_low > args.levels_high {
+        eprintln!("Error: --levels-low must not be above --levels-high");
+        std::process::exit(1);
+    }
+
+    let levels = args
+        .auto_levels
+        .then(|| luminance_percentiles(&img, args.levels_low, args.levels_high, &args.luminance));
+
+    let tone = Tone {
+        brightness: args.brightness,
+        contrast: args.contrast,
+        gamma: args.gamma,
+        levels,
+    };
+
+    if report {
+        eprintln!(
+            "Tone: brightness={:+.2} contrast={:.2} gamma={:.2}",
+            tone.brightness, tone.contrast, tone.gamma
+        );
+        if let Some((low, high)) = levels {
+            eprintln!(
+                "Auto-levels: luminance {:.0}-{:.0} ({}%-{}%) stretched to 0-255",
+                low, high, args.levels_low, args.levels_high
+            );
+        }
+    }
+
+    if tone.is_identity() {
+        img
+    } else {
+        adjust_tone(&img, &tone)
+    }
+}
+
+/// Glyphs edge mode can emit: the glyph table, or the light and heavy basic sets
+fn edge_charset(args: &Args) -> String {
+    match edge_glyph_table(args) {
+        Some(table) => table.into_iter().collect(),
+        None => {
+            let mut charset = String::new();
+            push_distinct(&mut charset, EDGE_ASCII_CHARS.iter().chain(STRONG_EDGE_ASCII_CHARS).map(|&b| b as char));
+            charset
+        }
+    }
+}
+
+/// Append the characters not yet in a charset, keeping their order
+fn push_distinct(charset: &mut String, chars: impl IntoIterator<Item = char>) {
+    for ch in chars {
+        if !charset.contains(ch) {
+            charset.push(ch);
+        }
+    }
+}
+
+/// Fine or user-supplied edge glyph table, if one is selected
+fn edge_glyph_table(args: &Args) -> Option<Vec<char>> {
+    match (&args.edge_glyph_table, &args.edge_glyphs) {
+        (Some(table), _) => Some(table.chars().collect()),
+        (None, EdgeGlyphs::Fine) => Some(FINE_EDGE_GLYPHS.chars().collect()),
+        (None, EdgeGlyphs::Basic) => None,
+    }
+}
+
+/// Detect edges at the resolution chosen by --edge-scale and return one
+/// edge entry per character cell
+///
+/// At scale 1 detection runs on the resized grid directly. Otherwise it runs
+/// on the source image (scale 0) or the grid upscaled by the scale factor,
+/// and each cell's block is pooled into a dominant orientation. Equalization
+/// and tone adjustments are repeated on the detection image, with CLAHE tiles
+/// scaled to cover the same cells; `--filter-stage resized` filters are not.
+fn edge_map_for_grid(args: &Args, source: &DynamicImage, grid: &DynamicImage, invert: bool) -> EdgeMap {
+    let (cols, rows) = grid.dimensions();
+
+    let detect_img = match args.edge_scale {
+        1 => return detect_edges(args, grid, invert),
+        0 => source.clone(),
+        n => match (cols.checked_mul(n), rows.checked_mul(n)) {
+            (Some(width), Some(height)) => source.resize_exact(width, height, FilterType::Triangle),
+            _ => {
+                eprintln!("Error: --edge-scale {} is too large for a {}x{} grid", n, cols, rows);
+                std::process::exit(1);
+            }
+        },
+    };
+    let factor = (detect_img.width() / cols.max(1)).max(1);
+    let detect_img = equalize_and_tone(args, detect_img, args.clahe_tile.saturating_mul(factor), false);
+
+    if args.verbose {
+        eprintln!("Edges: detected at {}x{} px, pooled into {}x{} cells",
+            detect_img.width(), detect_img.height(), cols, rows);
+    }
+
+    pool_edges(&detect_edges(args, &detect_img, invert), cols, rows)
+}
+
+/// Run the selected edge detector, reporting automatically chosen thresholds
+fn detect_edges(args: &Args, img: &DynamicImage, invert: bool) -> EdgeMap {
+    let auto = match args.edge_auto {
+        EdgeAuto::Otsu => Threshold::Otsu,
+        EdgeAuto::Density => Threshold::Density(args.edge_density),
+    };
+    let is_auto = args.edge_threshold == EdgeThreshold::Auto;
+    let threshold = match (&args.edge_algo, args.edge_threshold) {
+        (_, EdgeThreshold::Auto) => auto,
+        (EdgeAlgo::Canny, EdgeThreshold::Fixed(_)) => Threshold::Fixed(args.edge_high),
+        (EdgeAlgo::Sobel, EdgeThreshold::Fixed(t)) => Threshold::Fixed(t),
+    };
+
+    let options = EdgeOptions {
+        operator: args.edge_operator.clone(),
+        kernel_size: args.edge_kernel,
+        sigma: args.edge_sigma,
+        threshold,
+        low: args.edge_low,
+        weight: args.edge_weight,
+        invert,
+        luminance: args.luminance.clone(),
+    };
+
+    match (&args.edge_algo, &args.edge_operator) {
+        (EdgeAlgo::Canny, EdgeOperator::Log) => {
+            eprintln!("Error: --edge-algo canny needs a gradient operator, not log");
+            std::process::exit(1);
+        }
+        (EdgeAlgo::Canny, _) if !is_auto && args.edge_low > args.edge_high => {
+            eprintln!("Error: --edge-low must not be above --edge-high");
+            std::process::exit(1);
+        }
+        (EdgeAlgo::Canny, _) => {
+            let (edges, low, high) = canny_edge_detection(img, &options);
+            if is_auto {
+                eprintln!("Edge thresholds: --edge-low {} --edge-high {}", low, high);
+            }
+            edges
+        }
+        (EdgeAlgo::Sobel, operator) => {
+            let (edges, chosen) = if *operator == EdgeOperator::Log {
+                log_edge_detection(img, &options)
+            } else {
+                gradient_edge_detection(img, &options)
+            };
+            if is_auto {
+                eprintln!("Edge threshold: --edge-threshold {}", chosen);
+            }
+            edges
+        }
+    }
 }
@@ -1,6 +1,7 @@
 //! Edge Detection Module
 //!
-//! Implements Sobel edge detection for preprocessing images before ASCII conversion.
+//! Implements gradient (Sobel, Scharr, Prewitt, Roberts), Laplacian-of-Gaussian and
+//! Canny edge detection for preprocessing images before ASCII conversion.
 //! Uses both edge magnitude AND direction to map to appropriate ASCII characters.
 //!
 //! ## How Sobel Works
@@ -9,10 +10,12 @@
 //! 2. Calculate gradient magnitude: sqrt(Gx² + Gy²)
 //! 3. Calculate gradient direction: atan2(Gy, Gx)
 //! 4. Map direction to characters: | for vertical, - for horizontal, /\ for diagonals
-//! 5. Use magnitude to modulate brightness
+//! 5. Use magnitude to pick glyph weight and modulate brightness
 
-use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
-use std::f32::consts::PI;
+use image::{DynamicImage, GenericImageView};
+
+use crate::cli::EdgeOperator;
+use crate::types::{calculate_luminance, EdgeMap, EdgePixel, LuminanceModel};
 
 /// Sobel horizontal gradient kernel (Gx) - detects vertical edges
 const SOBEL_GX: [[i32; 3]; 3] = [
@@ -28,108 +31,661 @@ const SOBEL_GY: [[i32; 3]; 3] = [
     [ 1,  2,  1],
 ];
 
-/// Apply Sobel edge detection with directional encoding
+/// Scharr kernels - like Sobel with better rotational symmetry
+const SCHARR_GX: [[i32; 3]; 3] = [
+    [ -3, 0,  3],
+    [-10, 0, 10],
+    [ -3, 0,  3],
+];
+
+const SCHARR_GY: [[i32; 3]; 3] = [
+    [-3, -10, -3],
+    [ 0,   0,  0],
+    [ 3,  10,  3],
+];
+
+/// Prewitt kernels - unweighted box smoothing across the derivative
+const PREWITT_GX: [[i32; 3]; 3] = [
+    [-1, 0, 1],
+    [-1, 0, 1],
+    [-1, 0, 1],
+];
+
+const PREWITT_GY: [[i32; 3]; 3] = [
+    [-1, -1, -1],
+    [ 0,  0,  0],
+    [ 1,  1,  1],
+];
+
+/// Roberts cross, combined into x/y form on the 2×2 block below-right of
+/// the center: Gx = D1 + D2 and Gy = D1 - D2 for the two diagonal
+/// differences D1 (↘) and D2 (↗)
+const ROBERTS_GX: [[i32; 3]; 3] = [
+    [0,  0, 0],
+    [0, -1, 1],
+    [0, -1, 1],
+];
+
+const ROBERTS_GY: [[i32; 3]; 3] = [
+    [0,  0,  0],
+    [0, -1, -1],
+    [0,  1,  1],
+];
+
+/// 5×5 Sobel: [1 4 6 4 1] smoothing × [-1 -2 0 2 1] derivative
+const SOBEL5_GX: [[i32; 5]; 5] = [
+    [-1,  -2, 0,  2, 1],
+    [-4,  -8, 0,  8, 4],
+    [-6, -12, 0, 12, 6],
+    [-4,  -8, 0,  8, 4],
+    [-1,  -2, 0,  2, 1],
+];
+
+const SOBEL5_GY: [[i32; 5]; 5] = transpose(&SOBEL5_GX);
+
+/// 5×5 Scharr: integer approximation of the optimized 5-tap filters,
+/// [1 10 20 10 1] smoothing × [-1 -4 0 4 1] derivative
+const SCHARR5_GX: [[i32; 5]; 5] = [
+    [ -1,  -4, 0,  4,  1],
+    [-10, -40, 0, 40, 10],
+    [-20, -80, 0, 80, 20],
+    [-10, -40, 0, 40, 10],
+    [ -1,  -4, 0,  4,  1],
+];
+
+const SCHARR5_GY: [[i32; 5]; 5] = transpose(&SCHARR5_GX);
+
+/// 5×5 Prewitt: box smoothing × [-2 -1 0 1 2] derivative
+const PREWITT5_GX: [[i32; 5]; 5] = [
+    [-2, -1, 0, 1, 2],
+    [-2, -1, 0, 1, 2],
+    [-2, -1, 0, 1, 2],
+    [-2, -1, 0, 1, 2],
+    [-2, -1, 0, 1, 2],
+];
+
+const PREWITT5_GY: [[i32; 5]; 5] = transpose(&PREWITT5_GX);
+
+/// 3×3 Laplacian, applied after Gaussian blur to form a Laplacian of Gaussian
+const LAPLACIAN_3: [[i32; 3]; 3] = [
+    [0,  1, 0],
+    [1, -4, 1],
+    [0,  1, 0],
+];
+
+/// 5×5 Laplacian of Gaussian approximation
+const LOG_5: [[i32; 5]; 5] = [
+    [ 0,  0, -1,  0,  0],
+    [ 0, -1, -2, -1,  0],
+    [-1, -2, 16, -2, -1],
+    [ 0, -1, -2, -1,  0],
+    [ 0,  0, -1,  0,  0],
+];
+
+/// Transpose a kernel, turning a Gx kernel into its Gy counterpart
+const fn transpose<const N: usize>(kernel: &[[i32; N]; N]) -> [[i32; N]; N] {
+    let mut out = [[0; N]; N];
+    let mut y = 0;
+    while y < N {
+        let mut x = 0;
+        while x < N {
+            out[x][y] = kernel[y][x];
+            x += 1;
+        }
+        y += 1;
+    }
+    out
+}
+
+/// Per-pixel gradient of the luminance channel
+struct Gradient {
+    width: u32,
+    height: u32,
+    /// Gradient magnitude (clamped to 0-255)
+    magnitude: Vec<f32>,
+    /// Gradient direction in radians, from atan2(Gy, Gx)
+    angle: Vec<f32>,
+}
+
+/// How the edge threshold is chosen
+#[derive(Clone, Copy, Debug)]
+pub enum Threshold {
+    /// Use the given value (0-255)
+    Fixed(u8),
+    /// Otsu's method on the magnitude histogram
+    Otsu,
+    /// Keep roughly this percentage of pixels as edges
+    Density(f32),
+}
+
+/// Settings shared by all edge detectors
+#[derive(Clone, Debug)]
+pub struct EdgeOptions {
+    /// Gradient kernel pair (Sobel, Scharr, Prewitt, Roberts; LoG uses its own path)
+    pub operator: EdgeOperator,
+    /// 3 or 5 (5×5 variants exist for Sobel, Scharr, Prewitt and LoG)
+    pub kernel_size: u8,
+    /// Gaussian pre-blur sigma for Canny and LoG (0 disables the blur)
+    pub sigma: f32,
+    /// Minimum edge strength, fixed or chosen from the magnitude histogram.
+    /// For Canny this is the strong threshold.
+    pub threshold: Threshold,
+    /// Canny weak threshold, used when `threshold` is fixed
+    pub low: u8,
+    /// Multiplier applied to magnitudes when comparing against thresholds;
+    /// above 1.0 more pixels become edges. Automatic thresholds are chosen
+    /// from the unweighted magnitudes.
+    pub weight: f32,
+    /// Detect on inverted luminance, matching `--invert` in the brightness
+    /// path. Magnitude is unchanged and the gradient points the other way,
+    /// so only the fine glyph table, whose glyphs depend on which side is
+    /// darker, changes; the basic glyphs are the same for both directions.
+    pub invert: bool,
+    /// Luminance model the gradients are computed on
+    pub luminance: LuminanceModel,
+}
+
+/// Apply gradient edge detection with directional encoding
 ///
-/// # Arguments
-/// * `img` - Input image
-/// * `threshold` - Minimum edge strength to keep (0-255)
+/// # Returns
+/// Edge map and the threshold that was applied
+pub fn gradient_edge_detection(img: &DynamicImage, options: &EdgeOptions) -> (EdgeMap, u8) {
+    let gradient = operator_gradient(img, options);
+    // A zero threshold would turn every pixel, flat ones included, into an edge
+    let threshold = resolve_threshold(options.threshold, &gradient.magnitude, gradient.magnitude.len()).max(1);
+
+    let edges = build_edge_map(img, &gradient, options.weight, |i| {
+        gradient.magnitude[i] * options.weight >= threshold as f32
+    });
+    (edges, threshold)
+}
+
+/// Apply Laplacian-of-Gaussian edge detection with zero-crossing search
+///
+/// The blurred image is convolved with a Laplacian (3×3 after the blur, or
+/// a 5×5 LoG kernel); edges sit where the response changes sign between
+/// neighbours, and the jump across the crossing must reach the threshold.
+/// Automatic thresholds only consider pixels on a zero crossing. Direction
+/// comes from the Sobel gradient so the usual directional characters still
+/// apply.
+///
+/// # Returns
+/// Edge map and the threshold that was applied
+pub fn log_edge_detection(img: &DynamicImage, options: &EdgeOptions) -> (EdgeMap, u8) {
+    let blurred = if options.sigma > 0.0 { img.blur(options.sigma) } else { img.clone() };
+    let (width, height) = blurred.dimensions();
+
+    // Laplacian response, and the divisor that maps a unit step's
+    // zero-crossing jump back to 0-255
+    let (response, scale) = if options.kernel_size == 5 {
+        (convolve_plane(&blurred, &LOG_5, &options.luminance), 10.0)
+    } else {
+        (convolve_plane(&blurred, &LAPLACIAN_3, &options.luminance), 2.0)
+    };
+
+    let mut gradient = kernel_gradient(&blurred, &SOBEL_GX, &SOBEL_GY, options.invert, &options.luminance);
+    let radius = options.kernel_size.max(3) as u32 / 2;
+
+    for y in radius..height.saturating_sub(radius + 1) {
+        for x in radius..width.saturating_sub(radius + 1) {
+            let i = (y * width + x) as usize;
+            let here = response[i];
+
+            // Strongest sign change towards the right/lower neighbours
+            let mut jump: f32 = 0.0;
+            for (ox, oy) in [(1, 0), (0, 1), (1, 1)] {
+                let there = response[((y + oy) * width + x + ox) as usize];
+                if (here < 0.0) != (there < 0.0) {
+                    jump = jump.max((here - there).abs());
+                }
+            }
+
+            gradient.magnitude[i] = (jump / scale).min(255.0);
+        }
+    }
+
+    let crossings: Vec<f32> = gradient.magnitude.iter().copied().filter(|&m| m > 0.0).collect();
+    let threshold = resolve_threshold(options.threshold, &crossings, gradient.magnitude.len()).max(1);
+
+    let edges = build_edge_map(img, &gradient, options.weight, |i| {
+        gradient.magnitude[i] * options.weight >= threshold as f32
+    });
+    (edges, threshold)
+}
+
+/// Apply Canny edge detection with directional encoding
+///
+/// Produces thin, connected contours instead of the thick bands left by a
+/// single Sobel threshold:
+/// 1. Gaussian blur to suppress noise
+/// 2. Gradient magnitude and direction
+/// 3. Non-maximum suppression along the gradient direction
+/// 4. Double-threshold hysteresis: pixels above the strong threshold are
+///    edges, pixels above the weak one are edges only when connected to one
+///
+/// Automatic methods pick the strong threshold from the thinned magnitudes
+/// and set the weak threshold to half of it.
 ///
 /// # Returns
-/// RGBA image where RGB encodes edge direction and alpha encodes magnitude
-pub fn sobel_edge_detection(img: &DynamicImage, threshold: u8) -> DynamicImage {
+/// Edge map and the (low, high) thresholds that were applied
+pub fn canny_edge_detection(img: &DynamicImage, options: &EdgeOptions) -> (EdgeMap, u8, u8) {
+    let blurred = if options.sigma > 0.0 { img.blur(options.sigma) } else { img.clone() };
+    let gradient = operator_gradient(&blurred, options);
+    let thin = non_maximum_suppression(&gradient);
+
+    let (low, high) = match options.threshold {
+        Threshold::Fixed(high) => (options.low, high),
+        auto => {
+            let ridges: Vec<f32> = thin.iter().copied().filter(|&m| m > 0.0).collect();
+            let high = resolve_threshold(auto, &ridges, thin.len()).max(1);
+            (high / 2, high)
+        }
+    };
+    let weighted: Vec<f32> = thin.iter().map(|&m| m * options.weight).collect();
+    let edges = hysteresis(&gradient, &weighted, low as f32, high as f32);
+
+    // Colors come from the unblurred image
+    (build_edge_map(img, &gradient, options.weight, |i| edges[i]), low, high)
+}
+
+/// Pool a fine edge map into a coarser grid of `cols` × `rows` cells
+///
+/// Each cell covers a block of the fine map. A cell is an edge when enough
+/// of its block is (about half a block edge's worth of pixels, so a line
+/// crossing the block counts but isolated specks do not). Its orientation
+/// is the magnitude-weighted average of the doubled edge angles, which
+/// treats opposite gradient directions as the same line; polarity is then
+/// restored from the mean gradient vector. Magnitude and color are averaged
+/// over the block's edge pixels (color over all pixels for non-edge cells).
+pub fn pool_edges(fine: &EdgeMap, cols: u32, rows: u32) -> EdgeMap {
+    let mut pixels = Vec::with_capacity((cols * rows) as usize);
+
+    for cy in 0..rows {
+        let y0 = cy * fine.height / rows;
+        let y1 = ((cy + 1) * fine.height / rows).max(y0 + 1).min(fine.height);
+
+        for cx in 0..cols {
+            let x0 = cx * fine.width / cols;
+            let x1 = ((cx + 1) * fine.width / cols).max(x0 + 1).min(fine.width);
+
+            let mut count = 0u32;
+            let mut magnitude = 0.0;
+            let (mut dx2, mut dy2) = (0.0, 0.0);
+            let (mut dx, mut dy) = (0.0, 0.0);
+            let mut edge_rgb = [0.0f32; 3];
+            let mut all_rgb = [0.0f32; 3];
+
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let p = fine.get(x, y);
+                    for (sum, c) in all_rgb.iter_mut().zip([p.r, p.g, p.b]) {
+                        *sum += c as f32;
+                    }
+                    if !p.is_edge {
+                        continue;
+                    }
+
+                    count += 1;
+                    magnitude += p.magnitude;
+                    dx2 += p.magnitude * (2.0 * p.angle).cos();
+                    dy2 += p.magnitude * (2.0 * p.angle).sin();
+                    dx += p.magnitude * p.angle.cos();
+                    dy += p.magnitude * p.angle.sin();
+                    for (sum, c) in edge_rgb.iter_mut().zip([p.r, p.g, p.b]) {
+                        *sum += c as f32;
+                    }
+                }
+            }
+
+            let block = (x1 - x0) * (y1 - y0);
+            let min_count = ((x1 - x0).min(y1 - y0) / 2).max(1);
+            let is_edge = count >= min_count;
+
+            // Dominant line orientation, flipped to agree with the mean gradient
+            let mut angle = f32::atan2(dy2, dx2) / 2.0;
+            if angle.cos() * dx + angle.sin() * dy < 0.0 {
+                angle += std::f32::consts::PI;
+            }
+
+            let (rgb, n) = if count > 0 { (edge_rgb, count) } else { (all_rgb, block) };
+            let [r, g, b] = rgb.map(|c| (c / n as f32) as u8);
+
+            pixels.push(EdgePixel {
+                magnitude: if count > 0 { magnitude / count as f32 } else { 0.0 },
+                angle,
+                is_edge,
+                r,
+                g,
+                b,
+            });
+        }
+    }
+
+    EdgeMap { width: cols, height: rows, pixels }
+}
+
+/// Turn a threshold choice into a concrete value
+///
+/// # Arguments
+/// * `threshold` - Fixed value or automatic method
+/// * `values` - Candidate magnitudes (0-255) the threshold is chosen from
+/// * `total` - Pixel count the density percentage refers to
+fn resolve_threshold(threshold: Threshold, values: &[f32], total: usize) -> u8 {
+    let mut histogram = [0u32; 256];
+    for &v in values {
+        histogram[v.clamp(0.0, 255.0) as usize] += 1;
+    }
+
+    match threshold {
+        Threshold::Fixed(t) => t,
+        Threshold::Otsu => otsu_threshold(&histogram),
+        Threshold::Density(pct) => {
+            // Walk down from the strongest magnitudes until enough pixels are kept
+            let target = (total as f32 * pct.clamp(0.0, 100.0) / 100.0).ceil() as u32;
+            let mut kept = 0;
+            for t in (0..256).rev() {
+                kept += histogram[t];
+                if kept >= target {
+                    return t as u8;
+                }
+            }
+            0
+        }
+    }
+}
+
+/// Otsu's method: the threshold maximizing between-class variance
+///
+/// Values at or above the returned threshold form the upper class.
+pub fn otsu_threshold(histogram: &[u32; 256]) -> u8 {
+    let total: u32 = histogram.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+
+    let sum_all: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+    let mut sum_below = 0.0;
+    let mut count_below = 0u32;
+    let mut best = (0.0, 0u8);
+
+    for (t, &n) in histogram.iter().enumerate() {
+        count_below += n;
+        sum_below += t as f64 * n as f64;
+        let count_above = total - count_below;
+        if count_below == 0 || count_above == 0 {
+            continue;
+        }
+
+        let mean_below = sum_below / count_below as f64;
+        let mean_above = (sum_all - sum_below) / count_above as f64;
+        let variance = count_below as f64 * count_above as f64 * (mean_below - mean_above).powi(2);
+        if variance > best.0 {
+            // Pixels strictly above t form the edge class
+            best = (variance, (t + 1).min(255) as u8);
+        }
+    }
+
+    best.1
+}
+
+/// Compute the gradient with the selected operator
+///
+/// The Laplacian-of-Gaussian has no gradient pair of its own, so it falls
+/// back to Sobel here (used for direction only).
+fn operator_gradient(img: &DynamicImage, options: &EdgeOptions) -> Gradient {
+    let (invert, luminance) = (options.invert, &options.luminance);
+    match (&options.operator, options.kernel_size) {
+        (EdgeOperator::Sobel | EdgeOperator::Log, 5) => kernel_gradient(img, &SOBEL5_GX, &SOBEL5_GY, invert, luminance),
+        (EdgeOperator::Sobel | EdgeOperator::Log, _) => kernel_gradient(img, &SOBEL_GX, &SOBEL_GY, invert, luminance),
+        (EdgeOperator::Scharr, 5) => kernel_gradient(img, &SCHARR5_GX, &SCHARR5_GY, invert, luminance),
+        (EdgeOperator::Scharr, _) => kernel_gradient(img, &SCHARR_GX, &SCHARR_GY, invert, luminance),
+        (EdgeOperator::Prewitt, 5) => kernel_gradient(img, &PREWITT5_GX, &PREWITT5_GY, invert, luminance),
+        (EdgeOperator::Prewitt, _) => kernel_gradient(img, &PREWITT_GX, &PREWITT_GY, invert, luminance),
+        // Roberts cross is inherently 2×2
+        (EdgeOperator::Roberts, _) => kernel_gradient(img, &ROBERTS_GX, &ROBERTS_GY, invert, luminance),
+    }
+}
+
+/// Compute the gradient of every interior pixel with an N×N kernel pair
+/// (the border of N/2 pixels stays zero)
+///
+/// Magnitudes are normalized to the Sobel 3×3 scale by the kernel's total
+/// positive weight, so thresholds mean the same thing for every operator.
+fn kernel_gradient<const N: usize>(
+    img: &DynamicImage,
+    kernel_x: &[[i32; N]; N],
+    kernel_y: &[[i32; N]; N],
+    invert: bool,
+    luminance: &LuminanceModel,
+) -> Gradient {
     let (width, height) = img.dimensions();
-    let mut output = RgbaImage::new(width, height);
-    
-    // Process interior pixels (skip 1-pixel border)
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            // Apply Sobel kernels on luminance
-            let gx = convolve_3x3_luminance(img, x, y, &SOBEL_GX);
-            let gy = convolve_3x3_luminance(img, x, y, &SOBEL_GY);
-            
+    let len = (width * height) as usize;
+    let mut magnitude = vec![0.0; len];
+    let mut angle = vec![0.0; len];
+
+    let positive: i32 = kernel_x.iter().flatten().filter(|&&k| k > 0).sum();
+    let scale = 4.0 / positive as f32;
+    let radius = (N / 2) as u32;
+
+    // Process interior pixels (skip border)
+    for y in radius..height.saturating_sub(radius) {
+        for x in radius..width.saturating_sub(radius) {
+            // Apply kernels on luminance
+            let mut gx = convolve_luminance(img, x, y, kernel_x, luminance) as f32 * scale;
+            let mut gy = convolve_luminance(img, x, y, kernel_y, luminance) as f32 * scale;
+            if invert {
+                gx = -gx;
+                gy = -gy;
+            }
+
+            let i = (y * width + x) as usize;
             // Calculate edge magnitude using Euclidean norm
-            let magnitude = ((gx * gx + gy * gy) as f32).sqrt();
-            let magnitude = magnitude.min(255.0) as u8;
-            
-            if magnitude >= threshold {
-                // Calculate edge direction
-                let angle = (gy as f32).atan2(gx as f32);
-                
-                // Get original color
-                let [r, g, b, _] = img.get_pixel(x, y).0;
-                
-                // Encode direction in RGB for ASCII mapping
-                // We'll decode this in convert.rs to pick the right character
-                let dir_encoded = encode_direction(angle);
-                
-                // Use original color but encode direction info in alpha channel for now
-                // Actually, let's use a simple encoding: brightness = magnitude
-                output.put_pixel(x, y, Rgba([r, g, b, dir_encoded]));
-            } else {
-                // No edge - black
-                output.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            magnitude[i] = (gx * gx + gy * gy).sqrt().min(255.0);
+            angle[i] = gy.atan2(gx);
+        }
+    }
+
+    Gradient { width, height, magnitude, angle }
+}
+
+/// Convolve every interior pixel's luminance with an N×N kernel
+fn convolve_plane<const N: usize>(img: &DynamicImage, kernel: &[[i32; N]; N], luminance: &LuminanceModel) -> Vec<f32> {
+    let (width, height) = img.dimensions();
+    let mut out = vec![0.0; (width * height) as usize];
+    let radius = (N / 2) as u32;
+
+    for y in radius..height.saturating_sub(radius) {
+        for x in radius..width.saturating_sub(radius) {
+            out[(y * width + x) as usize] = convolve_luminance(img, x, y, kernel, luminance) as f32;
+        }
+    }
+
+    out
+}
+
+/// Keep only pixels whose magnitude is a local maximum along the gradient
+fn non_maximum_suppression(gradient: &Gradient) -> Vec<f32> {
+    let Gradient { width, height, .. } = *gradient;
+    let mut thin = vec![0.0; gradient.magnitude.len()];
+
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let i = (y * width + x) as usize;
+            let m = gradient.magnitude[i];
+            if m == 0.0 {
+                continue;
+            }
+
+            // Quantize the gradient direction to one of 4 neighbour axes
+            let mut deg = gradient.angle[i].to_degrees();
+            if deg < 0.0 {
+                deg += 180.0;
+            }
+            let (dx, dy): (i32, i32) = match deg {
+                d if !(22.5..157.5).contains(&d) => (1, 0),
+                d if d < 67.5 => (1, 1),
+                d if d < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+
+            let at = |ox: i32, oy: i32| {
+                let nx = (x as i32 + ox) as u32;
+                let ny = (y as i32 + oy) as u32;
+                gradient.magnitude[(ny * width + nx) as usize]
+            };
+
+            if m >= at(dx, dy) && m >= at(-dx, -dy) {
+                thin[i] = m;
             }
         }
     }
-    
-    // Borders remain black
+
+    thin
+}
+
+/// Double-threshold hysteresis: grow strong edges through connected weak ones
+fn hysteresis(gradient: &Gradient, thin: &[f32], low: f32, high: f32) -> Vec<bool> {
+    let Gradient { width, height, .. } = *gradient;
+    let mut edges = vec![false; thin.len()];
+    let mut stack: Vec<usize> = Vec::new();
+
+    for (i, &m) in thin.iter().enumerate() {
+        if m >= high && m > 0.0 {
+            edges[i] = true;
+            stack.push(i);
+        }
+    }
+
+    // Flood fill from strong pixels into 8-connected weak pixels
+    while let Some(i) = stack.pop() {
+        let x = (i as u32 % width) as i32;
+        let y = (i as u32 / width) as i32;
+
+        for oy in -1..=1 {
+            for ox in -1..=1 {
+                let nx = x + ox;
+                let ny = y + oy;
+                if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let n = (ny as u32 * width + nx as u32) as usize;
+                if !edges[n] && thin[n] >= low && thin[n] > 0.0 {
+                    edges[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+/// Build the edge map: weighted magnitude, direction and original color
+/// for every pixel, flagged where `is_edge` holds (borders are never edges)
+fn build_edge_map<F>(img: &DynamicImage, gradient: &Gradient, weight: f32, is_edge: F) -> EdgeMap
+where
+    F: Fn(usize) -> bool,
+{
+    let Gradient { width, height, .. } = *gradient;
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
     for y in 0..height {
         for x in 0..width {
-            if x == 0 || y == 0 || x == width-1 || y == height-1 {
-                output.put_pixel(x, y, Rgba([0, 0, 0, 255]));
-            }
+            let i = (y * width + x) as usize;
+
+            // Get original color
+            let [r, g, b, _] = img.get_pixel(x, y).0;
+
+            pixels.push(EdgePixel {
+                magnitude: (gradient.magnitude[i] * weight).min(255.0),
+                angle: gradient.angle[i],
+                is_edge: is_edge(i),
+                r,
+                g,
+                b,
+            });
         }
     }
-    
-    DynamicImage::ImageRgba8(output)
-}
-
-/// Encode edge direction as a value 0-255
-/// Divides 360 degrees into 8 sectors for 8 possible edge characters
-fn encode_direction(angle: f32) -> u8 {
-    // Normalize angle to 0-2π
-    let normalized = if angle < 0.0 { angle + 2.0 * PI } else { angle };
-    
-    // Divide into 8 sectors (45 degrees each)
-    // 0: → (horizontal right)
-    // 1: ↗ (diagonal up-right) 
-    // 2: ↑ (vertical up)
-    // 3: ↖ (diagonal up-left)
-    // 4: ← (horizontal left)
-    // 5: ↙ (diagonal down-left)
-    // 6: ↓ (vertical down)
-    // 7: ↘ (diagonal down-right)
-    
-    let sector = ((normalized / (PI / 4.0) + 0.5) as u8) % 8;
-    
-    // Map to character indices in EDGE_ASCII_CHARS = "|/-\\+*. "
-    match sector {
-        0 | 4 => 2, // - horizontal
-        1 | 5 => 1, // / diagonal
-        2 | 6 => 0, // | vertical
-        3 | 7 => 3, // \ diagonal
-        _ => 4,     // + fallback
-    }
-}
-
-/// Perform 3x3 convolution at a single pixel using luminance
-fn convolve_3x3_luminance(img: &DynamicImage, x: u32, y: u32, kernel: &[[i32; 3]; 3]) -> i32 {
+
+    EdgeMap { width, height, pixels }
+}
+
+/// Perform N×N convolution at a single pixel using luminance
+///
+/// The pixel must be at least N/2 pixels away from every border.
+fn convolve_luminance<const N: usize>(
+    img: &DynamicImage,
+    x: u32,
+    y: u32,
+    kernel: &[[i32; N]; N],
+    luminance: &LuminanceModel,
+) -> i32 {
     let mut sum: i32 = 0;
-    
-    for ky in 0..3 {
-        for kx in 0..3 {
-            let px = x + kx - 1;
-            let py = y + ky - 1;
-            
+    let radius = (N / 2) as u32;
+
+    for (ky, row) in kernel.iter().enumerate() {
+        for (kx, &kernel_value) in row.iter().enumerate() {
+            let px = x + kx as u32 - radius;
+            let py = y + ky as u32 - radius;
+
             let [r, g, b, _] = img.get_pixel(px, py).0;
-            
-            // Luminance
-            let lum = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as i32;
-            let kernel_value = kernel[ky as usize][kx as usize];
-            
+
+            let lum = calculate_luminance(r, g, b, luminance) as i32;
+
             sum += lum * kernel_value;
         }
     }
-    
+
     sum
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn options(threshold: Threshold) -> EdgeOptions {
+        EdgeOptions {
+            operator: EdgeOperator::Sobel,
+            kernel_size: 3,
+            sigma: 0.0,
+            threshold,
+            low: 0,
+            weight: 1.0,
+            invert: false,
+            luminance: LuminanceModel::Bt601,
+        }
+    }
+
+    #[test]
+    fn otsu_threshold_separates_two_peaks() {
+        let mut histogram = [0u32; 256];
+        histogram[10] = 50;
+        histogram[200] = 50;
+        // The first value of the upper class
+        assert_eq!(otsu_threshold(&histogram), 11);
+    }
+
+    #[test]
+    fn otsu_threshold_on_flat_or_empty_histogram_is_zero() {
+        let mut histogram = [0u32; 256];
+        assert_eq!(otsu_threshold(&histogram), 0);
+        histogram[0] = 64;
+        assert_eq!(otsu_threshold(&histogram), 0);
+    }
+
+    #[test]
+    fn resolve_threshold_on_flat_magnitudes() {
+        let flat = [0.0; 64];
+        assert_eq!(resolve_threshold(Threshold::Otsu, &flat, flat.len()), 0);
+        assert_eq!(resolve_threshold(Threshold::Fixed(42), &flat, flat.len()), 42);
+    }
+
+    #[test]
+    fn flat_image_has_no_gradient_edges() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([90, 90, 90, 255])));
+        let (edges, threshold) = gradient_edge_detection(&img, &options(Threshold::Otsu));
+        assert_eq!(threshold, 1);
+        assert!(edges.pixels.iter().all(|p| !p.is_edge));
+    }
+}
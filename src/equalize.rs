@@ -0,0 +1,189 @@
+//! Histogram Equalization Module
+//!
+//! Redistributes luminance so each character bucket in the ramp is used
+//! roughly evenly, which helps low-contrast scans and portraits.
+//!
+//! Two methods are provided:
+//! - Global histogram equalization over the whole image
+//! - CLAHE (contrast-limited adaptive histogram equalization), which
+//!   equalizes each tile separately, clips the histogram to limit noise
+//!   amplification and bilinearly blends neighbouring tile mappings
+//!
+//! Only luminance is remapped and hue is kept: for the luma models each
+//! pixel's RGB is scaled by the ratio of new to old luminance, for the linear
+//! model the same is done in linear light, and for OKLab the lightness is
+//! replaced while the a/b chroma axes are left alone.
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::color::{linear_to_srgb, oklab_to_srgb, srgb_to_linear, srgb_to_oklab};
+use crate::types::{calculate_luminance, LuminanceModel};
+
+/// Apply global histogram equalization on the luminance channel
+pub fn equalize_histogram(img: &DynamicImage, luminance: &LuminanceModel) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let luma = luminance_plane(img, luminance);
+
+    let mut histogram = [0u32; 256];
+    for &l in &luma {
+        histogram[l as usize] += 1;
+    }
+    let lut = cdf_lut(&histogram);
+
+    remap_luminance(img, |x, y| lut[luma[(y * width + x) as usize] as usize] as f32, width, height, luminance)
+}
+
+/// Apply contrast-limited adaptive histogram equalization
+///
+/// # Arguments
+/// * `img` - Input image
+/// * `tile` - Tile edge length in pixels
+/// * `clip` - Clip limit as a multiple of the average bin count (1.0 = no
+///   contrast boost, higher = stronger local contrast)
+/// * `luminance` - Luminance model that is equalized
+pub fn clahe(img: &DynamicImage, tile: u32, clip: f32, luminance: &LuminanceModel) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let tile = tile.max(1);
+    let luma = luminance_plane(img, luminance);
+
+    let tiles_x = width.div_ceil(tile);
+    let tiles_y = height.div_ceil(tile);
+
+    // Build one clipped equalization table per tile
+    let mut luts = Vec::with_capacity((tiles_x * tiles_y) as usize);
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let mut histogram = [0u32; 256];
+            let mut count = 0u32;
+            for y in ty * tile..((ty + 1) * tile).min(height) {
+                for x in tx * tile..((tx + 1) * tile).min(width) {
+                    histogram[luma[(y * width + x) as usize] as usize] += 1;
+                    count += 1;
+                }
+            }
+            clip_histogram(&mut histogram, count, clip);
+            luts.push(cdf_lut(&histogram));
+        }
+    }
+
+    let lut_at = |tx: u32, ty: u32, l: u8| luts[(ty * tiles_x + tx) as usize][l as usize] as f32;
+
+    remap_luminance(
+        img,
+        |x, y| {
+            let l = luma[(y * width + x) as usize];
+
+            // Position relative to tile centers
+            let fx = ((x as f32 + 0.5) / tile as f32 - 0.5).max(0.0);
+            let fy = ((y as f32 + 0.5) / tile as f32 - 0.5).max(0.0);
+            let x0 = (fx as u32).min(tiles_x - 1);
+            let y0 = (fy as u32).min(tiles_y - 1);
+            let x1 = (x0 + 1).min(tiles_x - 1);
+            let y1 = (y0 + 1).min(tiles_y - 1);
+            let wx = (fx - x0 as f32).clamp(0.0, 1.0);
+            let wy = (fy - y0 as f32).clamp(0.0, 1.0);
+
+            let top = lut_at(x0, y0, l) * (1.0 - wx) + lut_at(x1, y0, l) * wx;
+            let bottom = lut_at(x0, y1, l) * (1.0 - wx) + lut_at(x1, y1, l) * wx;
+            top * (1.0 - wy) + bottom * wy
+        },
+        width,
+        height,
+        luminance,
+    )
+}
+
+/// Luminance of every pixel in row-major order
+fn luminance_plane(img: &DynamicImage, luminance: &LuminanceModel) -> Vec<u8> {
+    img.pixels()
+        .map(|(_, _, pixel)| {
+            let [r, g, b, _] = pixel.0;
+            calculate_luminance(r, g, b, luminance) as u8
+        })
+        .collect()
+}
+
+/// Clip histogram bins at `clip` times the average and spread the excess evenly
+fn clip_histogram(histogram: &mut [u32; 256], count: u32, clip: f32) {
+    let limit = ((clip * count as f32 / 256.0) as u32).max(1);
+
+    let mut excess = 0;
+    for bin in histogram.iter_mut() {
+        if *bin > limit {
+            excess += *bin - limit;
+            *bin = limit;
+        }
+    }
+
+    let share = excess / 256;
+    let remainder = (excess % 256) as usize;
+    for (i, bin) in histogram.iter_mut().enumerate() {
+        *bin += share + u32::from(i < remainder);
+    }
+}
+
+/// Build an equalization lookup table from a histogram's cumulative distribution
+fn cdf_lut(histogram: &[u32; 256]) -> [u8; 256] {
+    let total: u32 = histogram.iter().sum();
+    let mut lut = [0u8; 256];
+    if total == 0 {
+        return lut;
+    }
+
+    let mut cumulative = 0u32;
+    for (entry, &n) in lut.iter_mut().zip(histogram.iter()) {
+        cumulative += n;
+        *entry = (cumulative as f32 * 255.0 / total as f32).round() as u8;
+    }
+
+    lut
+}
+
+/// Replace each pixel's luminance with `target(x, y)`, scaling RGB to match
+fn remap_luminance<F>(img: &DynamicImage, target: F, width: u32, height: u32, luminance: &LuminanceModel) -> DynamicImage
+where
+    F: Fn(u32, u32) -> f32,
+{
+    let mut output = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let old = calculate_luminance(r, g, b, luminance);
+        let new = target(x, y);
+
+        let rgb = relight([r, g, b], old, new, luminance);
+
+        output.put_pixel(x, y, Rgba([rgb[0], rgb[1], rgb[2], a]));
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Change a color's luminance from `old` to `new` in the space the model
+/// measures it in, so the result has the requested luminance unless clipped
+///
+/// Black has no hue to preserve and becomes a gray of the new luminance.
+fn relight(rgb: [u8; 3], old: f32, new: f32, luminance: &LuminanceModel) -> [u8; 3] {
+    match luminance {
+        // Luma is a weighted sum of the encoded channels
+        LuminanceModel::Bt601 | LuminanceModel::Bt709 => {
+            if old < 1.0 {
+                return [new as u8; 3];
+            }
+            let scale = new / old;
+            rgb.map(|c| (c as f32 * scale).round().min(255.0) as u8)
+        }
+        // Relative luminance is a weighted sum of the linear-light channels
+        LuminanceModel::Linear => {
+            if old < 1.0 {
+                return [linear_to_srgb(new / 255.0); 3];
+            }
+            let scale = new / old;
+            rgb.map(|c| linear_to_srgb(srgb_to_linear(c) * scale))
+        }
+        LuminanceModel::Oklab => {
+            let [_, a, b] = srgb_to_oklab(rgb);
+            oklab_to_srgb([new / 255.0, a, b])
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Image Filter Module
+//!
+//! Runs the steps of `--filter-chain` in order, either on the full-resolution
+//! source or on the image already resized to the character grid. Blur and
+//! sharpen come from `image::imageops`; posterize and threshold are per-pixel
+//! maps that leave the alpha channel as it is.
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::cli::Filter;
+use crate::types::{calculate_luminance, LuminanceModel};
+
+/// Apply each filter of a chain in order
+///
+/// # Arguments
+/// * `img` - Image to filter
+/// * `chain` - Filters to apply, first to last
+/// * `luminance` - Luminance model used by threshold
+///
+/// # Returns
+/// The filtered image, with the same dimensions
+pub fn apply_filters(img: DynamicImage, chain: &[Filter], luminance: &LuminanceModel) -> DynamicImage {
+    chain.iter().fold(img, |img, filter| match *filter {
+        Filter::Blur(sigma) => img.blur(sigma),
+        Filter::Sharpen(sigma) => img.unsharpen(sigma, 0),
+        Filter::Posterize(levels) => posterize(&img, levels),
+        Filter::Threshold(cutoff) => threshold(&img, cutoff, luminance),
+    })
+}
+
+/// Reduce every color channel to `levels` evenly spaced values
+fn posterize(img: &DynamicImage, levels: u8) -> DynamicImage {
+    let steps = (levels - 1) as f32;
+    map_pixels(img, |[r, g, b]| {
+        [r, g, b].map(|c| ((c as f32 / 255.0 * steps).round() / steps * 255.0).round() as u8)
+    })
+}
+
+/// Turn pixels black below `cutoff` luminance and white at or above it
+fn threshold(img: &DynamicImage, cutoff: u8, luminance: &LuminanceModel) -> DynamicImage {
+    map_pixels(img, |[r, g, b]| {
+        if calculate_luminance(r, g, b, luminance) >= cutoff as f32 {
+            [255, 255, 255]
+        } else {
+            [0, 0, 0]
+        }
+    })
+}
+
+/// Apply a color mapping to every pixel, keeping alpha
+fn map_pixels(img: &DynamicImage, f: impl Fn([u8; 3]) -> [u8; 3]) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let mut output = RgbaImage::new(width, height);
+
+    for (x, y, pixel) in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let [r, g, b] = f([r, g, b]);
+        output.put_pixel(x, y, Rgba([r, g, b, a]));
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
@@ -2,17 +2,31 @@
 //!
 //! Entry point for the img2ascii application.
 //! This program converts images into ASCII art with support for
-//! multiple output formats (terminal, text, HTML, ANSI).
+//! multiple output formats (terminal, text, HTML, ANSI, JSON).
 
 // Module declarations
+mod adjust;
+mod alpha;
+mod binary;
+mod blocks;
 mod cli;
 mod core;
 mod output;
+mod parse;
 mod types;
 mod convert;
 mod renderansi;
 mod renderhtml;
+mod renderjson;
 mod edge;
+mod equalize;
+mod filter;
+mod transform;
+mod font;
+mod color;
+mod palette;
+mod ramp;
+mod shape;
 
 // Main entry point
 fn main() -> std::io::Result<()> {
@@ -17,6 +17,7 @@ pub fn output_filename(image_path: &str, format: &OutputFormat) -> String {
         OutputFormat::Txt => "txt",
         OutputFormat::Html => "html",
         OutputFormat::Ansi => "ansi",
+        OutputFormat::Json => "json",
     };
 
     // Combine stem and extension into final filename
@@ -0,0 +1,311 @@
+//! Palette Quantization Module
+//!
+//! Maps each cell's color to the nearest entry of a fixed palette, measured
+//! in a perceptual color space (OKLab or CIELAB) so the choice follows how
+//! close colors look rather than raw RGB distance.
+//!
+//! Palettes are either built in (retro hardware and editor themes) or read
+//! from GIMP `.gpl` files or plain lists of hex colors. Quantization can be
+//! combined with Floyd-Steinberg error diffusion or ordered (Bayer) dithering
+//! across the cell grid.
+//!
+//! An adaptive palette can also be extracted from the cells themselves, by
+//! median cut or k-means clustering in OKLab.
+
+use crate::cli::{ColorMethod, ColorSpace, Dither};
+use crate::color::{distance_squared, oklab_to_srgb, parse_hex_color, srgb_to_lab, srgb_to_oklab};
+use crate::types::AsciiCell;
+
+/// Names of the built-in palettes
+pub const BUILTIN_PALETTES: [&str; 7] = ["cga", "ega", "gameboy", "pico8", "solarized", "nord", "gruvbox"];
+
+// CGA mode 4, palette 1 at high intensity
+const CGA: [u32; 4] = [0x000000, 0x55FFFF, 0xFF55FF, 0xFFFFFF];
+
+// EGA default palette (the 16 RGBI colors)
+const EGA: [u32; 16] = [
+    0x000000, 0x0000AA, 0x00AA00, 0x00AAAA, 0xAA0000, 0xAA00AA, 0xAA5500, 0xAAAAAA,
+    0x555555, 0x5555FF, 0x55FF55, 0x55FFFF, 0xFF5555, 0xFF55FF, 0xFFFF55, 0xFFFFFF,
+];
+
+// Original Game Boy LCD greens
+const GAMEBOY: [u32; 4] = [0x0F380F, 0x306230, 0x8BAC0F, 0x9BBC0F];
+
+const PICO8: [u32; 16] = [
+    0x000000, 0x1D2B53, 0x7E2553, 0x008751, 0xAB5236, 0x5F574F, 0xC2C3C7, 0xFFF1E8,
+    0xFF004D, 0xFFA300, 0xFFEC27, 0x00E436, 0x29ADFF, 0x83769C, 0xFF77A8, 0xFFCCAA,
+];
+
+const SOLARIZED: [u32; 16] = [
+    0x002B36, 0x073642, 0x586E75, 0x657B83, 0x839496, 0x93A1A1, 0xEEE8D5, 0xFDF6E3,
+    0xB58900, 0xCB4B16, 0xDC322F, 0xD33682, 0x6C71C4, 0x268BD2, 0x2AA198, 0x859900,
+];
+
+const NORD: [u32; 16] = [
+    0x2E3440, 0x3B4252, 0x434C5E, 0x4C566A, 0xD8DEE9, 0xE5E9F0, 0xECEFF4, 0x8FBCBB,
+    0x88C0D0, 0x81A1C1, 0x5E81AC, 0xBF616A, 0xD08770, 0xEBCB8B, 0xA3BE8C, 0xB48EAD,
+];
+
+// Gruvbox dark
+const GRUVBOX: [u32; 16] = [
+    0x282828, 0xCC241D, 0x98971A, 0xD79921, 0x458588, 0xB16286, 0x689D6A, 0xA89984,
+    0x928374, 0xFB4934, 0xB8BB26, 0xFABD2F, 0x83A598, 0xD3869B, 0x8EC07C, 0xEBDBB2,
+];
+
+/// 4x4 Bayer threshold matrix, shared with --binary dithering
+pub const BAYER_4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Look up a built-in palette by name (case-insensitive)
+pub fn builtin_palette(name: &str) -> Option<Vec<[u8; 3]>> {
+    let colors: &[u32] = match name.to_lowercase().as_str() {
+        "cga" => &CGA,
+        "ega" => &EGA,
+        "gameboy" => &GAMEBOY,
+        "pico8" => &PICO8,
+        "solarized" => &SOLARIZED,
+        "nord" => &NORD,
+        "gruvbox" => &GRUVBOX,
+        _ => return None,
+    };
+
+    Some(colors.iter().map(|&c| [(c >> 16) as u8, (c >> 8) as u8, c as u8]).collect())
+}
+
+// Lloyd iterations after the median cut seed
+const KMEANS_ITERATIONS: usize = 16;
+
+/// Build an adaptive palette of up to `count` colors from the cell colors
+///
+/// # Arguments
+/// * `cells` - Cell grid whose foreground and background colors are clustered
+/// * `count` - Maximum number of palette colors
+/// * `method` - Median cut, or k-means seeded with the median cut result
+///
+/// # Returns
+/// Distinct palette colors ordered from dark to light
+pub fn extract_palette(cells: &[Vec<AsciiCell>], count: usize, method: &ColorMethod) -> Vec<[u8; 3]> {
+    let points: Vec<[f32; 3]> = cells
+        .iter()
+        .flatten()
+        .filter(|c| !c.blank)
+        .flat_map(|c| std::iter::once([c.r, c.g, c.b]).chain(c.bg))
+        .map(srgb_to_oklab)
+        .collect();
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let mut centroids = median_cut(points.clone(), count);
+    if let ColorMethod::Kmeans = method {
+        refine_kmeans(&points, &mut centroids);
+    }
+
+    centroids.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    let mut palette: Vec<[u8; 3]> = centroids.into_iter().map(oklab_to_srgb).collect();
+    palette.dedup();
+    palette
+}
+
+/// Split the color box with the widest channel range at its median until
+/// there are `count` boxes, and return each box's mean
+fn median_cut(points: Vec<[f32; 3]>, count: usize) -> Vec<[f32; 3]> {
+    let mut boxes = vec![points];
+
+    while boxes.len() < count {
+        // Widest (box, axis) among boxes that can still be split
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .flat_map(|(i, b)| (0..3).map(move |axis| (i, axis, channel_range(b, axis))))
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        let (index, axis) = match widest {
+            Some((index, axis, range)) if range > 0.0 => (index, axis),
+            _ => break,
+        };
+
+        let mut split = boxes.swap_remove(index);
+        split.sort_by(|a, b| a[axis].total_cmp(&b[axis]));
+        let upper = split.split_off(split.len() / 2);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| mean(b)).collect()
+}
+
+/// Move each centroid to the mean of the points nearest to it
+fn refine_kmeans(points: &[[f32; 3]], centroids: &mut [[f32; 3]]) {
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![[0.0f32; 3]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+
+        for &point in points {
+            let nearest = (0..centroids.len())
+                .min_by(|&a, &b| {
+                    distance_squared(point, centroids[a]).total_cmp(&distance_squared(point, centroids[b]))
+                })
+                .unwrap_or(0);
+            for i in 0..3 {
+                sums[nearest][i] += point[i];
+            }
+            counts[nearest] += 1;
+        }
+
+        let mut moved = false;
+        for ((centroid, sum), &n) in centroids.iter_mut().zip(&sums).zip(&counts) {
+            // Empty clusters keep their previous position
+            if n == 0 {
+                continue;
+            }
+            let next = sum.map(|v| v / n as f32);
+            moved |= distance_squared(next, *centroid) > 1e-10;
+            *centroid = next;
+        }
+
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// Spread of one channel within a box
+fn channel_range(points: &[[f32; 3]], axis: usize) -> f32 {
+    let (min, max) = points
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| (lo.min(p[axis]), hi.max(p[axis])));
+    max - min
+}
+
+/// Mean of a non-empty set of colors
+fn mean(points: &[[f32; 3]]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    for p in points {
+        for i in 0..3 {
+            sum[i] += p[i];
+        }
+    }
+    sum.map(|v| v / points.len() as f32)
+}
+
+/// Parse a GIMP `.gpl` palette
+///
+/// # Returns
+/// The palette colors, or a message naming the first malformed line
+pub fn parse_gpl(text: &str) -> Result<Vec<[u8; 3]>, String> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "GIMP Palette" => {}
+        _ => return Err("missing \"GIMP Palette\" header".to_string()),
+    }
+
+    let mut colors = Vec::new();
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+
+        // "R G B [name]"
+        let channels: Vec<u8> = line.split_whitespace().take(3).filter_map(|v| v.parse().ok()).collect();
+        match channels[..] {
+            [r, g, b] => colors.push([r, g, b]),
+            _ => return Err(format!("line {}: expected \"R G B [name]\", got {:?}", i + 1, line)),
+        }
+    }
+
+    Ok(colors)
+}
+
+/// Parse a list of hex colors, one per line (`#rrggbb`, `rrggbb` or `#rgb`)
+///
+/// Blank lines and lines starting with `;` are skipped.
+pub fn parse_hex_list(text: &str) -> Result<Vec<[u8; 3]>, String> {
+    let mut colors = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        match parse_hex_color(line) {
+            Some(rgb) => colors.push(rgb),
+            None => return Err(format!("line {}: expected a hex color, got {:?}", i + 1, line)),
+        }
+    }
+
+    Ok(colors)
+}
+
+/// Replace every cell color, background included, with its nearest palette entry
+///
+/// # Arguments
+/// * `cells` - Cell grid, modified in place
+/// * `palette` - Target colors (must not be empty)
+/// * `space` - Perceptual space used to measure color distance
+/// * `dither` - Dithering applied across cells before picking colors
+pub fn quantize_cells(cells: &mut [Vec<AsciiCell>], palette: &[[u8; 3]], space: &ColorSpace, dither: &Dither) {
+    let to_space = match space {
+        ColorSpace::Oklab => srgb_to_oklab,
+        ColorSpace::Lab => srgb_to_lab,
+    };
+    let targets: Vec<[f32; 3]> = palette.iter().map(|&c| to_space(c)).collect();
+    let nearest = |rgb: [f32; 3]| {
+        let point = to_space(rgb.map(|c| c.round().clamp(0.0, 255.0) as u8));
+        (0..targets.len())
+            .min_by(|&a, &b| distance_squared(point, targets[a]).total_cmp(&distance_squared(point, targets[b])))
+            .map(|i| palette[i])
+            .unwrap_or([0, 0, 0])
+    };
+
+    // Ordered dithering offsets span roughly one palette step per channel
+    let spread = 255.0 / (palette.len() as f32).cbrt();
+
+    // Error diffusion works on a float copy of the colors
+    let mut work: Vec<Vec<[f32; 3]>> = cells
+        .iter()
+        .map(|row| row.iter().map(|c| [c.r, c.g, c.b].map(|v| v as f32)).collect())
+        .collect();
+
+    for y in 0..cells.len() {
+        for x in 0..cells[y].len() {
+            let mut want = work[y][x];
+            if let Dither::Bayer = dither {
+                let offset = (BAYER_4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                want = want.map(|c| c + offset * spread);
+            }
+
+            // Transparent cells have no color to quantize or diffuse
+            if cells[y][x].blank {
+                continue;
+            }
+
+            let [r, g, b] = nearest(want);
+            let cell = &mut cells[y][x];
+            cell.r = r;
+            cell.g = g;
+            cell.b = b;
+            // Backgrounds take their nearest color without dithering
+            cell.bg = cell.bg.map(|bg| nearest(bg.map(|c| c as f32)));
+
+            if let Dither::FloydSteinberg = dither {
+                let chosen = [r, g, b].map(|c| c as f32);
+                let error = [0, 1, 2].map(|i| want[i].clamp(0.0, 255.0) - chosen[i]);
+                let mut spread_error = |dx: isize, dy: usize, weight: f32| {
+                    let next = x.checked_add_signed(dx).and_then(|nx| work.get_mut(y + dy)?.get_mut(nx));
+                    if let Some(next) = next {
+                        for (c, e) in next.iter_mut().zip(error) {
+                            *c += e * weight;
+                        }
+                    }
+                };
+                spread_error(1, 0, 7.0 / 16.0);
+                spread_error(-1, 1, 3.0 / 16.0);
+                spread_error(0, 1, 5.0 / 16.0);
+                spread_error(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Art Parsing Module
+//!
+//! Reads previously produced output (plain text, ANSI escape text or JSON)
+//! back into an AsciiCell grid, so old art can be re-rendered in another
+//! format without the original image.
+//!
+//! ANSI parsing understands SGR foreground sequences in truecolor
+//! (`38;2;r;g;b`), 256-color (`38;5;n`) and 16-color (`30-37`, `90-97`)
+//! form, and the matching background sequences (`48;…`, `40-47`,
+//! `100-107`). Other escape sequences are skipped.
+
+use serde::de::Error as _;
+use serde::Deserialize;
+
+use crate::types::AsciiCell;
+
+/// Color given to cells that carry no color information (mid gray, so it
+/// stays visible on both light and dark backgrounds)
+pub const DEFAULT_FG: [u8; 3] = [128, 128, 128];
+
+/// The 16 standard terminal colors (xterm defaults)
+const BASIC_COLORS: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// Most cells (width × height after padding) JSON art may expand to, so a
+/// corrupt or hostile run length `n` cannot exhaust memory
+const MAX_JSON_CELLS: usize = 1 << 22;
+
+/// JSON document as written by `render_json` (options are ignored)
+#[derive(Deserialize)]
+struct JsonDocument {
+    encoding: String,
+    rows: Vec<Vec<JsonCell>>,
+}
+
+#[derive(Deserialize)]
+struct JsonCell {
+    ch: char,
+    rgb: Option<[u8; 3]>,
+    bg: Option<[u8; 3]>,
+    n: Option<usize>,
+}
+
+/// Parses plain text into cells with the default color
+pub fn parse_text(text: &str) -> Vec<Vec<AsciiCell>> {
+    let [r, g, b] = DEFAULT_FG;
+    let rows = text
+        .lines()
+        .map(|line| line.chars().map(|ch| AsciiCell::new(ch, r, g, b)).collect())
+        .collect();
+
+    pad_rows(rows)
+}
+
+/// Parses ANSI-colored text into cells, tracking the current colors
+pub fn parse_ansi(text: &str) -> Vec<Vec<AsciiCell>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut fg = DEFAULT_FG;
+    let mut bg = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // Only CSI sequences (ESC [) are meaningful here
+                if chars.peek() != Some(&'[') {
+                    continue;
+                }
+                chars.next();
+
+                // Collect parameter bytes up to the final byte (0x40-0x7E)
+                let mut params = String::new();
+                let mut final_byte = None;
+                for p in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&p) {
+                        final_byte = Some(p);
+                        break;
+                    }
+                    params.push(p);
+                }
+
+                if final_byte == Some('m') {
+                    apply_sgr(&params, &mut fg, &mut bg);
+                }
+            }
+            '\n' => rows.push(std::mem::take(&mut row)),
+            '\r' => {}
+            _ => match bg {
+                Some(bg) => row.push(AsciiCell::with_background(c, fg, bg)),
+                None => {
+                    let [r, g, b] = fg;
+                    row.push(AsciiCell::new(c, r, g, b));
+                }
+            },
+        }
+    }
+
+    if !row.is_empty() {
+        rows.push(row);
+    }
+
+    pad_rows(rows)
+}
+
+/// Parses a JSON document produced by `--output json`
+pub fn parse_json(text: &str) -> Result<Vec<Vec<AsciiCell>>, serde_json::Error> {
+    let doc: JsonDocument = serde_json::from_str(text)?;
+    let rle = doc.encoding == "rle";
+
+    let too_large = || serde_json::Error::custom(format!("art expands to more than {} cells", MAX_JSON_CELLS));
+
+    // Rows are padded to the widest one, so the limit applies to width × height
+    let mut rows = Vec::with_capacity(doc.rows.len());
+    let mut width = 0;
+    for row in doc.rows {
+        let mut cells = Vec::new();
+        for cell in row {
+            let count = if rle { cell.n.unwrap_or(1) } else { 1 };
+            let row_width = cells.len().saturating_add(count).max(width);
+            if row_width.saturating_mul(rows.len() + 1) > MAX_JSON_CELLS {
+                return Err(too_large());
+            }
+            // Cells without a color are transparent
+            let parsed = match (cell.rgb, cell.bg) {
+                (Some(fg), Some(bg)) => AsciiCell::with_background(cell.ch, fg, bg),
+                (Some([r, g, b]), None) => AsciiCell::new(cell.ch, r, g, b),
+                (None, _) => AsciiCell::blank(),
+            };
+            cells.extend(std::iter::repeat_n(parsed, count));
+        }
+        width = width.max(cells.len());
+        rows.push(cells);
+        if width.saturating_mul(rows.len()) > MAX_JSON_CELLS {
+            return Err(too_large());
+        }
+    }
+
+    Ok(pad_rows(rows))
+}
+
+/// Apply a Select Graphic Rendition parameter list to the current colors
+fn apply_sgr(params: &str, fg: &mut [u8; 3], bg: &mut Option<[u8; 3]>) {
+    // An empty parameter list is equivalent to a reset
+    let codes: Vec<u32> = params
+        .split(';')
+        .map(|p| p.parse().unwrap_or(0))
+        .collect();
+
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => {
+                *fg = DEFAULT_FG;
+                *bg = None;
+            }
+            39 => *fg = DEFAULT_FG,
+            49 => *bg = None,
+            n @ 30..=37 => *fg = BASIC_COLORS[(n - 30) as usize],
+            n @ 90..=97 => *fg = BASIC_COLORS[(n - 90 + 8) as usize],
+            n @ 40..=47 => *bg = Some(BASIC_COLORS[(n - 40) as usize]),
+            n @ 100..=107 => *bg = Some(BASIC_COLORS[(n - 100 + 8) as usize]),
+            // Extended colors: 38 sets the foreground, 48 the background;
+            // values above 255 are ignored rather than wrapped
+            code @ (38 | 48) => {
+                let channel = |n: &u32| u8::try_from(*n).ok();
+                let (color, used) = match codes.get(i + 1) {
+                    Some(5) => (codes.get(i + 2).and_then(channel).map(xterm_256), 2),
+                    Some(2) => {
+                        let rgb = codes.get(i + 2..i + 5).and_then(|c| {
+                            Some([channel(&c[0])?, channel(&c[1])?, channel(&c[2])?])
+                        });
+                        (rgb, 4)
+                    }
+                    _ => (None, 0),
+                };
+                match (code, color) {
+                    (38, Some(color)) => *fg = color,
+                    (_, Some(color)) => *bg = Some(color),
+                    _ => {}
+                }
+                i += used;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Convert an xterm 256-color index to RGB
+fn xterm_256(n: u8) -> [u8; 3] {
+    match n {
+        0..=15 => BASIC_COLORS[n as usize],
+        16..=231 => {
+            // 6x6x6 color cube
+            const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+            let i = n - 16;
+            [
+                LEVELS[(i / 36) as usize],
+                LEVELS[(i / 6 % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            ]
+        }
+        // 24-step grayscale ramp
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            [v, v, v]
+        }
+    }
+}
+
+/// Pad ragged rows with blank cells so the grid is rectangular
+fn pad_rows(mut rows: Vec<Vec<AsciiCell>>) -> Vec<Vec<AsciiCell>> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    for row in &mut rows {
+        row.resize(width, AsciiCell::blank());
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sgr(params: &str) -> ([u8; 3], Option<[u8; 3]>) {
+        let (mut fg, mut bg) = ([1, 2, 3], Some([4, 5, 6]));
+        apply_sgr(params, &mut fg, &mut bg);
+        (fg, bg)
+    }
+
+    #[test]
+    fn sgr_extended_colors() {
+        assert_eq!(sgr("38;2;10;20;30"), ([10, 20, 30], Some([4, 5, 6])));
+        assert_eq!(sgr("48;2;10;20;30"), ([1, 2, 3], Some([10, 20, 30])));
+        assert_eq!(sgr("38;5;196"), ([255, 0, 0], Some([4, 5, 6])));
+        assert_eq!(sgr("48;5;232"), ([1, 2, 3], Some([8, 8, 8])));
+    }
+
+    #[test]
+    fn sgr_basic_colors_and_resets() {
+        assert_eq!(sgr("31;42"), ([205, 0, 0], Some([0, 205, 0])));
+        assert_eq!(sgr("97;104"), ([255, 255, 255], Some([92, 92, 255])));
+        assert_eq!(sgr("39;49"), (DEFAULT_FG, None));
+        assert_eq!(sgr("0"), (DEFAULT_FG, None));
+        assert_eq!(sgr(""), (DEFAULT_FG, None));
+    }
+
+    #[test]
+    fn sgr_ignores_out_of_range_colors() {
+        assert_eq!(sgr("38;2;300;10;10"), ([1, 2, 3], Some([4, 5, 6])));
+        assert_eq!(sgr("48;5;256"), ([1, 2, 3], Some([4, 5, 6])));
+        assert_eq!(sgr("38;2;70000;0;0"), ([1, 2, 3], Some([4, 5, 6])));
+        // Parameters after a rejected color are still applied
+        assert_eq!(sgr("38;5;999;44"), ([1, 2, 3], Some([0, 0, 238])));
+    }
+
+    #[test]
+    fn json_rle_expands_runs() {
+        let text = r#"{"encoding":"rle","rows":[[{"ch":"a","rgb":[1,2,3],"n":3},{"ch":" "}]]}"#;
+        let cells = parse_json(text).unwrap();
+        assert_eq!(cells[0].len(), 4);
+        assert!(cells[0][..3].iter().all(|c| c.ch == 'a' && !c.blank));
+        assert!(cells[0][3].blank);
+    }
+
+    #[test]
+    fn json_rle_rejects_huge_runs() {
+        let text = r#"{"encoding":"rle","rows":[[{"ch":"a","rgb":[1,2,3],"n":1000000000000}]]}"#;
+        assert!(parse_json(text).is_err());
+    }
+
+    #[test]
+    fn json_rle_limits_the_whole_grid() {
+        let row = r#"[{"ch":"a","rgb":[0,0,0],"n":65536}]"#;
+        let doc = |rows: usize| format!(r#"{{"encoding":"rle","rows":[{}]}}"#, vec![row; rows].join(","));
+        assert!(parse_json(&doc(64)).is_ok());
+        assert!(parse_json(&doc(65)).is_err());
+
+        // Short rows are padded to the widest one
+        let text = format!(r#"{{"encoding":"rle","rows":[{},{}]}}"#, row, vec!["[]"; 64].join(","));
+        assert!(parse_json(&text).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Character Ramp Module
+//!
+//! Builds a brightness ramp from a user-supplied charset. Each character's
+//! ink coverage is measured on the embedded bitmap font, the characters are
+//! sorted from densest to sparsest, and each is placed at the perceived
+//! lightness (CIE L*) of a cell with that much ink. Pixels then map to the
+//! character whose lightness is nearest, so steps follow the actual glyphs
+//! rather than an even split of the charset.
+
+use crate::font::coverage;
+
+/// A brightness ramp ordered from densest to sparsest character
+pub struct Ramp {
+    /// Characters from densest to sparsest
+    pub chars: Vec<char>,
+    /// Normalized darkness of each character (1.0 = densest, 0.0 = sparsest)
+    pub levels: Vec<f32>,
+    /// Characters dropped because an earlier one has the same coverage,
+    /// paired with the character that was kept
+    pub duplicates: Vec<(char, char)>,
+    /// Characters without a glyph in the embedded font
+    pub missing: Vec<char>,
+    // Index into `chars` for each darkness value 0-255
+    lut: [usize; 256],
+}
+
+impl Ramp {
+    /// Measure, sort and space a charset
+    ///
+    /// # Returns
+    /// The ramp, or None if fewer than two characters with distinct coverage remain
+    pub fn from_charset(charset: &str) -> Option<Ramp> {
+        let mut measured: Vec<(char, f32)> = Vec::new();
+        let mut duplicates = Vec::new();
+        let mut missing = Vec::new();
+
+        for ch in charset.chars() {
+            if measured.iter().any(|&(c, _)| c == ch) {
+                continue;
+            }
+            match coverage(ch) {
+                Some(ink) => match measured.iter().find(|&&(_, other)| other == ink) {
+                    Some(&(kept, _)) => duplicates.push((ch, kept)),
+                    None => measured.push((ch, ink)),
+                },
+                None => missing.push(ch),
+            }
+        }
+
+        if measured.len() < 2 {
+            return None;
+        }
+
+        // Densest first, like ASCII_CHARS
+        measured.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let lightness: Vec<f32> = measured.iter().map(|&(_, ink)| cie_lightness(1.0 - ink)).collect();
+        let darkest = lightness[0];
+        let lightest = lightness[lightness.len() - 1];
+        let levels: Vec<f32> = lightness.iter().map(|l| (lightest - l) / (lightest - darkest)).collect();
+
+        let mut lut = [0; 256];
+        for (darkness, entry) in lut.iter_mut().enumerate() {
+            let target = darkness as f32 / 255.0;
+            *entry = (0..levels.len())
+                .min_by(|&a, &b| (levels[a] - target).abs().total_cmp(&(levels[b] - target).abs()))
+                .unwrap_or(0);
+        }
+
+        Some(Ramp {
+            chars: measured.into_iter().map(|(ch, _)| ch).collect(),
+            levels,
+            duplicates,
+            missing,
+            lut,
+        })
+    }
+
+    /// Character for a darkness value (0 = white, 255 = black)
+    pub fn pick(&self, darkness: u8) -> char {
+        self.chars[self.lut[darkness as usize]]
+    }
+}
+
+/// CIE L* lightness of a relative luminance, scaled to 0.0-1.0
+fn cie_lightness(y: f32) -> f32 {
+    if y > 216.0 / 24389.0 {
+        (116.0 * y.cbrt() - 16.0) / 100.0
+    } else {
+        y * 24389.0 / 27.0 / 100.0
+    }
+}
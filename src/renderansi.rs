@@ -2,6 +2,8 @@
 //!
 //! Renders ASCII art with ANSI color codes for terminal display.
 //! Supports both colored and monochrome output using ANSI escape sequences.
+//! Cells with a background color (block element modes) also set it with
+//! `48;2;r;g;b`.
 
 use crate::types::AsciiCell;
 
@@ -11,11 +13,17 @@ pub fn render_ansi(cells: &[Vec<AsciiCell>], color: bool) -> String {
 
     for row in cells {
         for cell in row {
-            if color {
-                out.push_str(&format!(
-                    "\x1b[38;2;{};{};{}m{}\x1b[0m",
-                    cell.r, cell.g, cell.b, cell.ch
-                ));
+            if color && !cell.blank {
+                match cell.bg {
+                    Some([br, bg, bb]) => out.push_str(&format!(
+                        "\x1b[38;2;{};{};{};48;2;{};{};{}m{}\x1b[0m",
+                        cell.r, cell.g, cell.b, br, bg, bb, cell.ch
+                    )),
+                    None => out.push_str(&format!(
+                        "\x1b[38;2;{};{};{}m{}\x1b[0m",
+                        cell.r, cell.g, cell.b, cell.ch
+                    )),
+                }
             } else {
                 out.push(cell.ch);
             }
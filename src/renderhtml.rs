@@ -2,11 +2,21 @@
 //!
 //! Renders ASCII art as a styled HTML document with colored characters.
 //! Generates a complete HTML page with inline CSS for proper display.
+//! When the colors were quantized to a palette, each palette entry becomes a
+//! CSS variable and a class, which keeps the document small and lets the
+//! colors be restyled by editing a single block. Cells with a background
+//! color (block element modes) also get a `background-color`, or a `bN`
+//! class when it is in the palette.
 
+use crate::cli::Theme;
 use crate::types::AsciiCell;
 
 /// Renders ASCII cells as an HTML document with inline styles
-pub fn render_html(cells: &[Vec<AsciiCell>]) -> String {
+///
+/// When a theme is given the page background and text color are set to
+/// match it; otherwise the browser defaults are kept. With a palette, cells
+/// whose color is in it use the class `cN` instead of an inline style.
+pub fn render_html(cells: &[Vec<AsciiCell>], theme: Option<Theme>, palette: Option<&[[u8; 3]]>) -> String {
     let mut html = String::new();
 
     html.push_str(r#"<!DOCTYPE html>
@@ -19,7 +29,33 @@ pre {
   line-height: 1;
   font-size: 8px;
 }
-</style>
+"#);
+
+    match theme {
+        Some(Theme::Light) => html.push_str("body { background: #ffffff; color: #000000; }\n"),
+        Some(Theme::Dark) => html.push_str("body { background: #000000; color: #ffffff; }\n"),
+        None => {}
+    }
+
+    let has_background = cells.iter().flatten().any(|cell| cell.bg.is_some());
+
+    if let Some(palette) = palette {
+        html.push_str(":root {\n");
+        for (i, [r, g, b]) in palette.iter().enumerate() {
+            html.push_str(&format!("  --c{}: #{:02x}{:02x}{:02x};\n", i, r, g, b));
+        }
+        html.push_str("}\n");
+        for i in 0..palette.len() {
+            html.push_str(&format!(".c{0} {{ color: var(--c{0}); }}\n", i));
+        }
+        if has_background {
+            for i in 0..palette.len() {
+                html.push_str(&format!(".b{0} {{ background-color: var(--c{0}); }}\n", i));
+            }
+        }
+    }
+
+    html.push_str(r#"</style>
 </head>
 <body>
 <pre>
@@ -27,13 +63,34 @@ pre {
 
     for row in cells {
         for cell in row {
-            html.push_str(&format!(
-                r#"<span style="color: rgb({},{},{})">{}</span>"#,
-                cell.r,
-                cell.g,
-                cell.b,
-                html_escape(cell.ch)
-            ));
+            if cell.blank {
+                html.push(' ');
+                continue;
+            }
+
+            let index = |color: [u8; 3]| palette.and_then(|p| p.iter().position(|&c| c == color));
+            let mut classes = Vec::new();
+            let mut styles = Vec::new();
+
+            match index([cell.r, cell.g, cell.b]) {
+                Some(i) => classes.push(format!("c{}", i)),
+                None => styles.push(format!("color: rgb({},{},{})", cell.r, cell.g, cell.b)),
+            }
+            if let Some(bg) = cell.bg {
+                match index(bg) {
+                    Some(i) => classes.push(format!("b{}", i)),
+                    None => styles.push(format!("background-color: rgb({},{},{})", bg[0], bg[1], bg[2])),
+                }
+            }
+
+            html.push_str("<span");
+            if !classes.is_empty() {
+                html.push_str(&format!(r#" class="{}""#, classes.join(" ")));
+            }
+            if !styles.is_empty() {
+                html.push_str(&format!(r#" style="{}""#, styles.join("; ")));
+            }
+            html.push_str(&format!(">{}</span>", html_escape(cell.ch)));
         }
         html.push('\n');
     }
@@ -0,0 +1,95 @@
+//! JSON Rendering Module
+//!
+//! Serializes the ASCII cell grid as a structured JSON document so the
+//! converter can be driven from non-Rust tooling. The document records the
+//! grid dimensions, the character set, the options that produced it and
+//! every cell's character plus RGB color, optionally run-length encoded.
+//! Transparent cells have a null color, and cells from block element modes
+//! carry a `bg` background color.
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::types::AsciiCell;
+
+/// Top-level JSON document written for `--output json`
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    width: usize,
+    height: usize,
+    charset: &'a str,
+    options: &'a Args,
+    /// Either "cells" (one entry per cell) or "rle" (runs of identical cells)
+    encoding: &'static str,
+    rows: Vec<Vec<JsonCell>>,
+}
+
+/// A single cell, or a run of identical cells when `n` is present
+#[derive(Serialize)]
+struct JsonCell {
+    ch: char,
+    rgb: Option<[u8; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bg: Option<[u8; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
+}
+
+/// Renders ASCII cells as a JSON document
+///
+/// # Arguments
+/// * `cells` - The converted cell grid
+/// * `args` - Command-line options that produced the grid
+/// * `charset` - Character set used for the conversion
+/// * `rle` - Collapse consecutive identical cells into runs
+pub fn render_json(cells: &[Vec<AsciiCell>], args: &Args, charset: &str, rle: bool) -> String {
+    let rows = cells
+        .iter()
+        .map(|row| if rle { encode_runs(row) } else { encode_cells(row) })
+        .collect();
+
+    let doc = JsonDocument {
+        width: cells.first().map_or(0, |row| row.len()),
+        height: cells.len(),
+        charset,
+        options: args,
+        encoding: if rle { "rle" } else { "cells" },
+        rows,
+    };
+
+    // Serializing plain structs of strings and integers cannot fail
+    serde_json::to_string(&doc).expect("JSON serialization failed")
+}
+
+fn encode_cells(row: &[AsciiCell]) -> Vec<JsonCell> {
+    row.iter()
+        .map(|cell| JsonCell {
+            ch: cell.ch,
+            rgb: cell_rgb(cell),
+            bg: cell.bg,
+            n: None,
+        })
+        .collect()
+}
+
+/// Collapse consecutive cells with the same character and colors into runs
+fn encode_runs(row: &[AsciiCell]) -> Vec<JsonCell> {
+    let mut runs: Vec<JsonCell> = Vec::new();
+
+    for cell in row {
+        let rgb = cell_rgb(cell);
+        match runs.last_mut() {
+            Some(run) if run.ch == cell.ch && run.rgb == rgb && run.bg == cell.bg => {
+                run.n = Some(run.n.unwrap_or(1) + 1);
+            }
+            _ => runs.push(JsonCell { ch: cell.ch, rgb, bg: cell.bg, n: Some(1) }),
+        }
+    }
+
+    runs
+}
+
+/// Color of a cell, or None for transparent cells
+fn cell_rgb(cell: &AsciiCell) -> Option<[u8; 3]> {
+    (!cell.blank).then_some([cell.r, cell.g, cell.b])
+}
@@ -0,0 +1,140 @@
+//! Shape Matching Module
+//!
+//! Picks each character by comparing the source block behind a cell with the
+//! rasterized glyphs of the embedded font, instead of mapping brightness to a
+//! ramp. A cell covering a diagonal edge gets `/` or `\`, a horizontal line
+//! gets `-` or `_`, and so on, which keeps outlines and lettering sharp.
+//!
+//! The input image is expected at glyph resolution: 8x16 subpixels per cell.
+//! Blocks are compared as ink coverage (0.0 = background, 1.0 = ink) using
+//! either mean squared error or the structural similarity index (SSIM).
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::cli::ShapeMetric;
+use crate::font::{rasterize, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::types::{calculate_luminance, AsciiCell, LuminanceModel};
+
+const BLOCK_SIZE: usize = GLYPH_WIDTH * GLYPH_HEIGHT;
+
+// SSIM stabilizing constants for a dynamic range of 1.0. They are well above
+// the usual 0.01 and 0.03 factors so JPEG noise in flat areas is not matched
+// as structure
+const SSIM_C1: f32 = 0.1 * 0.1;
+const SSIM_C2: f32 = 0.2 * 0.2;
+
+/// Rasterized glyph with the statistics SSIM needs
+struct Glyph {
+    ch: char,
+    coverage: [f32; BLOCK_SIZE],
+    mean: f32,
+    variance: f32,
+}
+
+/// Converts an image at glyph resolution into ASCII cells by shape
+///
+/// # Arguments
+/// * `img` - Image with 8x16 pixels per output cell
+/// * `charset` - Candidate characters; those without a font glyph are skipped
+/// * `metric` - Block comparison metric
+/// * `invert` - Treat bright pixels as ink (light text on a dark background)
+/// * `luminance` - Luminance model for ink coverage
+///
+/// # Returns
+/// The cell grid, or None if no character in `charset` has a glyph
+pub fn generate_ascii_by_shape(
+    img: &DynamicImage,
+    charset: &[char],
+    metric: &ShapeMetric,
+    invert: bool,
+    luminance: &LuminanceModel,
+) -> Option<Vec<Vec<AsciiCell>>> {
+    let glyphs: Vec<Glyph> = charset
+        .iter()
+        .filter_map(|&ch| {
+            let coverage = rasterize(ch)?;
+            let (mean, variance) = statistics(&coverage);
+            Some(Glyph { ch, coverage, mean, variance })
+        })
+        .collect();
+
+    if glyphs.is_empty() {
+        return None;
+    }
+
+    let cols = img.width() / GLYPH_WIDTH as u32;
+    let rows = img.height() / GLYPH_HEIGHT as u32;
+    let mut grid = Vec::with_capacity(rows as usize);
+
+    for row in 0..rows {
+        let mut cells = Vec::with_capacity(cols as usize);
+
+        for col in 0..cols {
+            let mut block = [0.0; BLOCK_SIZE];
+            let mut sum = [0u32; 3];
+
+            for (i, value) in block.iter_mut().enumerate() {
+                let x = col * GLYPH_WIDTH as u32 + (i % GLYPH_WIDTH) as u32;
+                let y = row * GLYPH_HEIGHT as u32 + (i / GLYPH_WIDTH) as u32;
+                let [r, g, b, _] = img.get_pixel(x, y).0;
+
+                let brightness = calculate_luminance(r, g, b, luminance) / 255.0;
+                *value = if invert { brightness } else { 1.0 - brightness };
+
+                sum[0] += r as u32;
+                sum[1] += g as u32;
+                sum[2] += b as u32;
+            }
+
+            let ch = best_match(&block, &glyphs, metric);
+            let [r, g, b] = sum.map(|c| (c / BLOCK_SIZE as u32) as u8);
+            cells.push(AsciiCell::new(ch, r, g, b));
+        }
+
+        grid.push(cells);
+    }
+
+    Some(grid)
+}
+
+/// Character whose glyph is most similar to the block
+fn best_match(block: &[f32; BLOCK_SIZE], glyphs: &[Glyph], metric: &ShapeMetric) -> char {
+    let (mean, variance) = statistics(block);
+    let score = |glyph: &Glyph| match metric {
+        // Negated so that higher is better for both metrics
+        ShapeMetric::Mse => -block
+            .iter()
+            .zip(glyph.coverage.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>(),
+        ShapeMetric::Ssim => ssim(block, mean, variance, glyph),
+    };
+
+    glyphs
+        .iter()
+        .map(|glyph| (glyph.ch, score(glyph)))
+        .fold((' ', f32::NEG_INFINITY), |best, candidate| {
+            if candidate.1 > best.1 { candidate } else { best }
+        })
+        .0
+}
+
+/// Structural similarity between a block and a glyph over a single window
+fn ssim(block: &[f32; BLOCK_SIZE], mean: f32, variance: f32, glyph: &Glyph) -> f32 {
+    let covariance = block
+        .iter()
+        .zip(glyph.coverage.iter())
+        .map(|(a, b)| (a - mean) * (b - glyph.mean))
+        .sum::<f32>()
+        / BLOCK_SIZE as f32;
+
+    ((2.0 * mean * glyph.mean + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+        / ((mean * mean + glyph.mean * glyph.mean + SSIM_C1) * (variance + glyph.variance + SSIM_C2))
+}
+
+/// Mean and variance of a block
+fn statistics(values: &[f32; BLOCK_SIZE]) -> (f32, f32) {
+    let mean = values.iter().sum::<f32>() / BLOCK_SIZE as f32;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / BLOCK_SIZE as f32;
+    (mean, variance)
+}
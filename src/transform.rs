@@ -0,0 +1,83 @@
+//! Geometry Preprocessing Module
+//!
+//! Rotates, flips, trims and crops the loaded image before its size in
+//! characters is worked out, so the aspect ratio always matches the part of
+//! the image that is actually converted.
+//!
+//! Operations run in this order: rotate, flip, trim, crop. Crop coordinates
+//! therefore refer to the image as it will appear in the output.
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::cli::{Crop, Flip};
+
+// Largest per-channel difference still treated as border color by --trim,
+// enough to absorb JPEG noise
+const TRIM_TOLERANCE: u8 = 16;
+
+/// Rotate an image clockwise by 90, 180 or 270 degrees
+pub fn rotate(img: DynamicImage, degrees: u16) -> DynamicImage {
+    match degrees {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Mirror an image horizontally or vertically
+pub fn flip(img: DynamicImage, direction: &Flip) -> DynamicImage {
+    match direction {
+        Flip::H => img.fliph(),
+        Flip::V => img.flipv(),
+    }
+}
+
+/// Remove borders that match the top-left pixel color
+///
+/// # Returns
+/// The trimmed image, or the input unchanged if every pixel is border colored
+pub fn trim(img: DynamicImage) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return img;
+    }
+    let border = img.get_pixel(0, 0).0;
+
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in img.pixels() {
+        let differs = pixel.0.iter().zip(border.iter()).any(|(a, b)| a.abs_diff(*b) > TRIM_TOLERANCE);
+        if differs {
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+    }
+
+    match bounds {
+        Some((x0, y0, x1, y1)) => img.crop_imm(x0, y0, x1 - x0 + 1, y1 - y0 + 1),
+        None => img,
+    }
+}
+
+/// Crop to a rectangle, clamped to the image bounds
+///
+/// # Returns
+/// The cropped image, or None if the rectangle lies outside the image or is empty
+pub fn crop(img: &DynamicImage, rect: &Crop) -> Option<DynamicImage> {
+    let (width, height) = img.dimensions();
+    let x = rect.x.resolve(width);
+    let y = rect.y.resolve(height);
+    if x >= width || y >= height {
+        return None;
+    }
+
+    let w = rect.width.resolve(width).min(width - x);
+    let h = rect.height.resolve(height).min(height - y);
+    if w == 0 || h == 0 {
+        return None;
+    }
+
+    Some(img.crop_imm(x, y, w, h))
+}
@@ -1,8 +1,14 @@
 //! Type Definitions Module
 //!
 //! Contains core data structures and constants used throughout the application,
-//! including the ASCII character set and the AsciiCell structure for storing
-//! character and color information.
+//! including the ASCII character set, the AsciiCell structure for storing
+//! character and color information, the EdgeMap produced by edge detection,
+//! and the luminance models shared by every brightness-based stage.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::color::{srgb_to_linear, srgb_to_oklab};
 
 // ASCII characters ordered from darkest to lightest
 pub const ASCII_CHARS: &[u8] = b"@%#*+=-:. ";
@@ -10,6 +16,15 @@ pub const ASCII_CHARS: &[u8] = b"@%#*+=-:. ";
 // Edge ASCII characters for edge detection mode (dense to sparse)
 pub const EDGE_ASCII_CHARS: &[u8] = b"|/-\\+*. ";
 
+// Heavy counterparts of the first four edge characters, used for strong edges
+pub const STRONG_EDGE_ASCII_CHARS: &[u8] = b"#X=X";
+
+// Fine edge glyphs for 16 gradient directions (22.5° apart, starting with the
+// gradient pointing right and turning clockwise on screen), then the corner
+// glyph. The gradient points from dark to light, so `_` is the bottom of a
+// dark shape, `‾` its top, and `(` `)` its left and right flanks.
+pub const FINE_EDGE_GLYPHS: &str = "|)//_\\\\(|(//‾\\\\)+";
+
 // Structure to hold ASCII character and its RGB color
 #[derive(Clone, Copy, Debug)]
 pub struct AsciiCell {
@@ -17,23 +32,104 @@ pub struct AsciiCell {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    /// Transparent cell with no color, rendered as a plain space
+    pub blank: bool,
+    /// Background color, set by block modes that paint both sides of a glyph
+    pub bg: Option<[u8; 3]>,
+}
+
+impl AsciiCell {
+    /// A colored character cell
+    pub fn new(ch: char, r: u8, g: u8, b: u8) -> Self {
+        AsciiCell { ch, r, g, b, blank: false, bg: None }
+    }
+
+    /// A character cell with both a foreground and a background color
+    pub fn with_background(ch: char, fg: [u8; 3], bg: [u8; 3]) -> Self {
+        let [r, g, b] = fg;
+        AsciiCell { ch, r, g, b, blank: false, bg: Some(bg) }
+    }
+
+    /// A transparent cell
+    pub fn blank() -> Self {
+        AsciiCell { ch: ' ', r: 0, g: 0, b: 0, blank: true, bg: None }
+    }
+}
+
+/// How brightness is computed from RGB, selected with --luminance
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LuminanceModel {
+    /// ITU-R BT.601 luma on gamma-encoded RGB
+    Bt601,
+    /// ITU-R BT.709 luma on gamma-encoded RGB
+    Bt709,
+    /// Relative luminance of linear-light RGB (darker midtones)
+    Linear,
+    /// OKLab perceptual lightness
+    Oklab,
+}
+
+/// Edge detection result for a single pixel
+#[derive(Clone, Copy, Debug)]
+pub struct EdgePixel {
+    /// Gradient magnitude after edge weighting (0-255)
+    pub magnitude: f32,
+    /// Gradient direction in radians from atan2(Gy, Gx), with y pointing
+    /// down; it points from darker towards lighter luminance
+    pub angle: f32,
+    /// Whether the detector kept this pixel as an edge
+    pub is_edge: bool,
+    /// Source pixel color
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Per-pixel edge information produced by the edge detectors
+///
+/// Unlike an encoded image, edge presence is an explicit flag, so edges on
+/// black pixels survive and non-edge pixels still carry their gradient.
+#[derive(Clone, Debug)]
+pub struct EdgeMap {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major pixels
+    pub pixels: Vec<EdgePixel>,
+}
+
+impl EdgeMap {
+    /// Edge information at (x, y)
+    pub fn get(&self, x: u32, y: u32) -> &EdgePixel {
+        &self.pixels[(y * self.width + x) as usize]
+    }
 }
 
-/// Calculate luminance (brightness) from RGB values using standard formula
+/// Calculate luminance (brightness) from RGB values
 ///
-/// Uses the ITU-R BT.601 luma coefficients which match human perception:
-/// - Red: 29.9%
-/// - Green: 58.7% (human eye is most sensitive to green)
-/// - Blue: 11.4%
+/// The model decides how the channels are weighted:
+/// - BT.601: 0.299 R + 0.587 G + 0.114 B on gamma-encoded values (classic luma)
+/// - BT.709: 0.2126 R + 0.7152 G + 0.0722 B on gamma-encoded values (HDTV luma)
+/// - Linear: BT.709 weights on decoded sRGB, i.e. relative luminance Y, so
+///   midtones come out darker than with luma
+/// - OKLab: perceptual lightness L, which tracks how bright colors look
 ///
 /// # Arguments
 /// * `r` - Red channel value (0-255)
 /// * `g` - Green channel value (0-255)
 /// * `b` - Blue channel value (0-255)
+/// * `model` - Luminance model
 ///
 /// # Returns
 /// Luminance value as f32 (0.0-255.0)
 #[inline]
-pub fn calculate_luminance(r: u8, g: u8, b: u8) -> f32 {
-    0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32
+pub fn calculate_luminance(r: u8, g: u8, b: u8, model: &LuminanceModel) -> f32 {
+    match model {
+        LuminanceModel::Bt601 => 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32,
+        LuminanceModel::Bt709 => 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32,
+        LuminanceModel::Linear => {
+            255.0 * (0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b))
+        }
+        LuminanceModel::Oklab => (255.0 * srgb_to_oklab([r, g, b])[0]).clamp(0.0, 255.0),
+    }
 }